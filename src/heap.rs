@@ -1,2 +1,679 @@
+pub mod binary;
 pub mod binomial;
+pub mod compare;
+pub mod cycliclist;
+pub mod fibonacci;
+mod handle;
+pub mod list;
 pub mod pairing;
+pub mod stable;
+
+use std::alloc::Layout;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+
+use compare::Compare;
+
+/// Basic priority queue. `delete_min` returns the element that comes first in the heap's order.
+pub trait Heap {
+    type Item;
+
+    fn insert(&mut self, item: Self::Item);
+
+    fn delete_min(&mut self) -> Option<Self::Item>;
+
+    fn peek(&self) -> Option<&Self::Item>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Heap that can absorb every element of another heap of the same type.
+pub trait MeldableHeap: Heap {
+    fn meld(&mut self, other: Self);
+}
+
+/// Error returned when a handle does not point to an element of the heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HeapError {
+    /// The element of the handle has been removed.
+    StaleHandle,
+    /// The handle was returned by another heap.
+    ForeignHandle,
+}
+
+impl fmt::Display for HeapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StaleHandle => f.write_str("the element of the handle has been removed"),
+            Self::ForeignHandle => f.write_str("the handle belongs to another heap"),
+        }
+    }
+}
+
+impl std::error::Error for HeapError {}
+
+/// Heap whose elements can be addressed after insertion.
+///
+/// Handles stay valid across `meld`. Using a handle whose element was removed, or one returned
+/// by an unrelated heap, fails with a [`HeapError`] and leaves the heap unchanged.
+pub trait AddressableHeap: Heap {
+    type Handle: Clone;
+
+    fn insert_with_handle(&mut self, item: Self::Item) -> Self::Handle;
+
+    /// Replaces the element pointed by `handle` with a smaller one.
+    fn decrease_key(&mut self, handle: &Self::Handle, item: Self::Item) -> Result<(), HeapError>;
+
+    /// Removes the element pointed by `handle` and returns it.
+    fn remove(&mut self, handle: Self::Handle) -> Result<Self::Item, HeapError>;
+
+    /// Replaces the element pointed by `handle` with a greater one.
+    fn increase_key(&mut self, handle: &Self::Handle, item: Self::Item) -> Result<(), HeapError>;
+
+    /// Replaces the element pointed by `handle` with `f(element)`, which may be smaller or greater.
+    fn update<F: FnOnce(&Self::Item) -> Self::Item>(
+        &mut self,
+        handle: &Self::Handle,
+        f: F,
+    ) -> Result<(), HeapError>;
+}
+
+/// Returns whether two sequences sorted by `cmp` hold the same elements.
+///
+/// Elements that `cmp` considers equivalent may come in any order, so each run of them is matched
+/// with `==` separately, in O(r²) for a run of length r. Only `PartialEq` is known of `T`, so the
+/// run cannot be sorted or hashed instead.
+fn sorted_multiset_eq<'a, T: PartialEq + 'a, C: Compare<T>>(
+    a: impl Iterator<Item = &'a T>,
+    b: impl Iterator<Item = &'a T>,
+    cmp: &C,
+) -> bool {
+    let (mut a, mut b) = (a.peekable(), b.peekable());
+    let (mut run_a, mut run_b) = (Vec::new(), Vec::new());
+    loop {
+        let (x, y) = match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(x), Some(y)) if cmp.compare(x, y) == Ordering::Equal => (x, y),
+            _ => return false,
+        };
+        run_a.clear();
+        run_b.clear();
+        run_a.push(x);
+        run_b.push(y);
+        run_a.extend(std::iter::from_fn(|| {
+            a.next_if(|n| cmp.compare(n, x) == Ordering::Equal)
+        }));
+        run_b.extend(std::iter::from_fn(|| {
+            b.next_if(|n| cmp.compare(n, x) == Ordering::Equal)
+        }));
+        if run_a.len() != run_b.len() {
+            return false;
+        }
+        for x in &run_a {
+            match run_b.iter().position(|y| x == y) {
+                Some(i) => run_b.swap_remove(i),
+                None => return false,
+            };
+        }
+    }
+}
+
+/// Feeds `state` with a hash of `items` that does not depend on their order.
+///
+/// Each item is hashed on its own and the results are summed, then the sum and the count are
+/// written to `state` as a single value.
+fn hash_unordered<'a, T: Hash + 'a, H: Hasher>(items: impl Iterator<Item = &'a T>, state: &mut H) {
+    let build = BuildHasherDefault::<DefaultHasher>::default();
+    let (len, sum) = items.fold((0u64, 0u64), |(len, sum), item| {
+        (len + 1, sum.wrapping_add(build.hash_one(item)))
+    });
+    state.write_u128(u128::from(len) << 64 | u128::from(sum));
+}
+
+/// Memory of a node whose element was moved out, handed from one heap to another when converting
+/// between them, so that the new node can be built in it.
+///
+/// The memory is released if it is dropped unused.
+struct Recycled {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl Recycled {
+    /// Takes over the memory of `node`.
+    ///
+    /// # Safety
+    /// `node` must have been allocated by the global allocator with the layout of `N`, and its
+    /// contents must not be used or dropped anymore.
+    unsafe fn new<N>(node: NonNull<N>) -> Self {
+        Self {
+            ptr: node.cast(),
+            layout: Layout::new::<N>(),
+        }
+    }
+
+    /// Moves `node` into the memory and returns it, allocated with the layout of `N`.
+    ///
+    /// The allocation is resized with `realloc` when the alignments agree, which is usually done
+    /// in place when it shrinks, and replaced otherwise.
+    fn reuse<N>(self, node: N) -> NonNull<N> {
+        let this = ManuallyDrop::new(self);
+        let layout = Layout::new::<N>();
+        let raw = unsafe {
+            if this.layout == layout {
+                this.ptr.as_ptr()
+            } else if this.layout.align() == layout.align() {
+                std::alloc::realloc(this.ptr.as_ptr(), this.layout, layout.size())
+            } else {
+                std::alloc::dealloc(this.ptr.as_ptr(), this.layout);
+                std::alloc::alloc(layout)
+            }
+        };
+        let Some(ptr) = NonNull::new(raw as *mut N) else {
+            std::alloc::handle_alloc_error(layout);
+        };
+        unsafe { ptr.as_ptr().write(node) };
+        ptr
+    }
+}
+
+impl Drop for Recycled {
+    fn drop(&mut self) {
+        unsafe { std::alloc::dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+
+    fn sort_with<H: MeldableHeap<Item = i32> + Default>(v: &[i32]) -> Vec<i32> {
+        let (left, right) = v.split_at(v.len() / 2);
+        let mut heap = H::default();
+        let mut other = H::default();
+        left.iter().for_each(|&x| heap.insert(x));
+        right.iter().for_each(|&x| other.insert(x));
+        heap.meld(other);
+        assert_eq!(heap.len(), v.len());
+        let mut sorted = Vec::with_capacity(v.len());
+        while let Some(x) = heap.delete_min() {
+            sorted.push(x);
+        }
+        assert!(heap.is_empty());
+        sorted
+    }
+
+    #[test]
+    fn generic_heapsort() {
+        let mut rng = rand::thread_rng();
+        let v: Vec<i32> = (0..200).map(|_| rng.gen_range(-100..100)).collect();
+        let mut expected = v.clone();
+        expected.sort();
+        assert_eq!(sort_with::<pairing::PairingHeap<_>>(&v), expected);
+        assert_eq!(sort_with::<binomial::BinomialHeap<_>>(&v), expected);
+        assert_eq!(sort_with::<fibonacci::FibonacciHeap<_>>(&v), expected);
+        assert_eq!(sort_with::<binary::MinBinaryHeap<_>>(&v), expected);
+    }
+
+    fn remove_with<H: AddressableHeap<Item = i32> + Default>() {
+        let mut heap = H::default();
+        let handles: Vec<_> = (0..200)
+            .map(|i| (i * 71 % 200, heap.insert_with_handle(i * 71 % 200)))
+            .collect();
+        for _ in 0..10 {
+            heap.delete_min();
+        }
+        let mut expected: Vec<i32> = (10..200).collect();
+        for (v, h) in handles.into_iter().filter(|&(v, _)| v >= 10 && v % 3 == 0) {
+            assert_eq!(heap.remove(h), Ok(v));
+            expected.retain(|&x| x != v);
+        }
+        assert_eq!(heap.len(), expected.len());
+        let mut sorted = Vec::new();
+        while let Some(x) = heap.delete_min() {
+            sorted.push(x);
+        }
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn generic_remove() {
+        remove_with::<pairing::PairingHeap<_>>();
+        remove_with::<binomial::BinomialHeap<_>>();
+        remove_with::<fibonacci::FibonacciHeap<_>>();
+    }
+
+    fn update_with<H: AddressableHeap<Item = i32> + Default>() {
+        let mut rng = rand::thread_rng();
+        let mut heap = H::default();
+        let mut live: Vec<_> = (0..200)
+            .map(|i| i * 71 % 200 * 4)
+            .map(|v| (heap.insert_with_handle(v), v))
+            .collect();
+        for _ in 0..10 {
+            let min = heap.delete_min().unwrap();
+            live.retain(|&(_, v)| v != min);
+        }
+        for _ in 0..3 {
+            for (h, v) in live.iter_mut() {
+                let old = *v;
+                // values stay distinct modulo 1000, so popped elements can be told apart
+                *v += 1000 * rng.gen_range(-3..=3);
+                if *v > old {
+                    heap.increase_key(h, *v).unwrap();
+                } else {
+                    heap.update(h, |_| *v).unwrap();
+                }
+            }
+            let min = heap.delete_min().unwrap();
+            live.retain(|&(_, v)| v != min);
+        }
+        let mut expected: Vec<i32> = live.iter().map(|&(_, v)| v).collect();
+        expected.sort();
+        let mut sorted = Vec::new();
+        while let Some(x) = heap.delete_min() {
+            sorted.push(x);
+        }
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn generic_update() {
+        update_with::<pairing::PairingHeap<_>>();
+        update_with::<binomial::BinomialHeap<_>>();
+        update_with::<fibonacci::FibonacciHeap<_>>();
+    }
+
+    fn checked_with<H: AddressableHeap<Item = i32> + MeldableHeap + Default>() {
+        let mut a = H::default();
+        let mut b = H::default();
+        let ha = a.insert_with_handle(5);
+        let hb = b.insert_with_handle(7);
+        let popped = a.insert_with_handle(1);
+        assert_eq!(a.delete_min(), Some(1));
+        assert_eq!(a.decrease_key(&popped, 0), Err(HeapError::StaleHandle));
+        assert_eq!(a.decrease_key(&hb, 0), Err(HeapError::ForeignHandle));
+        assert_eq!(b.remove(ha.clone()), Err(HeapError::ForeignHandle));
+        assert_eq!(
+            H::default().increase_key(&ha, 9),
+            Err(HeapError::ForeignHandle)
+        );
+
+        // handles of both heaps survive melds, also through a chain of them
+        a.meld(b);
+        let mut c = H::default();
+        let hc = c.insert_with_handle(4);
+        c.meld(a);
+        assert_eq!(c.decrease_key(&hb, 3), Ok(()));
+        assert_eq!(c.update(&ha, |v| v - 3), Ok(()));
+        assert_eq!(c.peek(), Some(&2));
+        assert_eq!(c.remove(hc.clone()), Ok(4));
+        assert_eq!(c.remove(hc), Err(HeapError::StaleHandle));
+        drop(c);
+        assert_eq!(H::default().remove(ha), Err(HeapError::StaleHandle));
+    }
+
+    #[test]
+    fn generic_checked_handles() {
+        checked_with::<pairing::PairingHeap<_>>();
+        checked_with::<binomial::BinomialHeap<_>>();
+        checked_with::<fibonacci::FibonacciHeap<_>>();
+    }
+
+    fn multiset_eq_with<H>()
+    where
+        H: AddressableHeap<Item = (i32, char)> + MeldableHeap + Eq + Hash,
+        H: FromIterator<(i32, char)> + Default,
+    {
+        let hash = |heap: &H| {
+            let mut hasher = DefaultHasher::new();
+            heap.hash(&mut hasher);
+            hasher.finish()
+        };
+        let items: Vec<_> = (0..100)
+            .map(|i| (i * 7 % 30, (b'a' + (i % 5) as u8) as char))
+            .collect();
+        let a: H = items.iter().copied().collect();
+        // same elements, built in another order and reshaped by a pop
+        let mut b = H::default();
+        for &x in items.iter().rev() {
+            b.insert(x);
+        }
+        let mut c = H::default();
+        c.insert((-1, 'z'));
+        b.meld(c);
+        assert_eq!(b.delete_min(), Some((-1, 'z')));
+        assert!(a == b);
+        assert_eq!(hash(&a), hash(&b));
+
+        // ties of the comparator are matched by value
+        let mut d: H = items.iter().copied().collect();
+        let h = d.insert_with_handle((3, 'q'));
+        assert!(a != d);
+        d.update(&h, |&(k, _)| (k, 'a')).unwrap();
+        assert!(a != d);
+        assert_eq!(d.remove(h), Ok((3, 'a')));
+        assert!(a == d);
+        d.insert((3, 'b'));
+        let mut e: H = items.iter().copied().collect();
+        e.insert((3, 'c'));
+        assert!(d != e);
+        assert!(H::default() == H::default());
+    }
+
+    #[test]
+    fn generic_multiset_eq() {
+        multiset_eq_with::<pairing::PairingHeap<_>>();
+        multiset_eq_with::<binomial::BinomialHeap<_>>();
+        multiset_eq_with::<pairing::PairingMap<_, _>>();
+        multiset_eq_with::<binomial::BinomialMap<_, _>>();
+    }
+
+    /// Tests of the inherent API that `PairingHeap` and `BinomialHeap` share, expanded in a
+    /// module named after the module of each heap.
+    macro_rules! inherent_tests {
+        ($($module:ident::$heap:ident),*) => {$(
+            mod $module {
+                #[allow(unused_imports)]
+                use crate::heap::$module::*;
+                #[allow(unused_imports)]
+                use crate::heap::{compare::*, HeapError};
+
+                #[test]
+                fn peek_test() {
+                    let mut heap = $heap::new();
+                    assert!(heap.peek().is_none());
+                    assert!(heap.peek_mut().is_none());
+                    for i in [7, 3, 9, 1, 5] {
+                        heap.insert(i);
+                    }
+                    assert_eq!(heap.peek(), Some(&1));
+                    *heap.peek_mut().unwrap() = 8;
+                    assert_eq!(heap.peek(), Some(&3));
+                    {
+                        let mut top = heap.peek_mut().unwrap();
+                        *top = 2;
+                    }
+                    assert_eq!(heap.peek(), Some(&2));
+                    assert_eq!(PeekMut::pop(heap.peek_mut().unwrap()), 2);
+                    assert_eq!(heap.push_pop(4), 4);
+                    assert_eq!(heap.push_pop(6), 5);
+                    assert_eq!(heap.replace_min(10), Some(6));
+                    let v: Vec<i32> = (0..heap.len())
+                        .map(|_| heap.delete_min().unwrap())
+                        .collect();
+                    assert_eq!(v, vec![7, 8, 9, 10]);
+                    assert_eq!(heap.replace_min(1), None);
+                    assert_eq!(heap.len(), 1);
+                }
+
+                #[test]
+                fn iter_test() {
+                    let mut heap = $heap::new();
+                    for i in (0..50).rev() {
+                        heap.insert(i);
+                    }
+                    heap.delete_min();
+                    assert_eq!(heap.iter().len(), 49);
+                    assert_eq!(heap.iter().sum::<i32>(), (1..50).sum());
+                    let mut v: Vec<i32> = (&heap).into_iter().copied().collect();
+                    v.sort();
+                    assert_eq!(v, (1..50).collect::<Vec<_>>());
+
+                    let mut drained: Vec<i32> = heap.drain().take(10).collect();
+                    assert!(heap.is_empty());
+                    assert_eq!(drained.len(), 10);
+                    heap.insert(3);
+                    heap.insert(1);
+                    drained = heap.into_iter().collect();
+                    drained.sort();
+                    assert_eq!(drained, vec![1, 3]);
+                }
+
+                #[test]
+                fn sorted_test() {
+                    let mut heap = $heap::new();
+                    for i in [4, 8, 1, 9, 3, 3, 7] {
+                        heap.insert(i);
+                    }
+                    let mut drain = heap.drain_sorted();
+                    assert_eq!(drain.len(), 7);
+                    assert_eq!(drain.next(), Some(1));
+                    assert_eq!(drain.next(), Some(3));
+                    drop(drain);
+                    assert!(heap.is_empty());
+
+                    for i in [4, 8, 1, 9, 3, 3, 7] {
+                        heap.insert(i);
+                    }
+                    let mut iter = heap.clone().into_iter_sorted();
+                    assert_eq!(iter.next(), Some(1));
+                    assert_eq!(iter.len(), 6);
+                    assert_eq!(heap.into_sorted_vec(), vec![1, 3, 3, 4, 7, 8, 9]);
+                }
+
+                #[test]
+                fn from_iter_test() {
+                    let mut heap: $heap<i32> = (0..100).rev().collect();
+                    assert_eq!(heap.len(), 100);
+                    heap.extend([-1, 200]);
+                    assert_eq!(heap.peek(), Some(&-1));
+                    assert_eq!(heap.len(), 102);
+                    assert_eq!(
+                        $heap::from([3, 1, 2]).into_sorted_vec(),
+                        vec![1, 2, 3]
+                    );
+                    assert_eq!($heap::from(vec![5, 4]).into_sorted_vec(), vec![4, 5]);
+                    assert!($heap::<i32>::default().is_empty());
+                }
+
+                #[test]
+                fn extend_panic_test() {
+                    let mut heap: $heap<i32> = [10, 4].into_iter().collect();
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        heap.extend((0..10).map(|i| if i < 3 { i * 3 } else { panic!() }))
+                    }));
+                    assert!(result.is_err());
+                    // the items pulled before the panic are in the heap
+                    assert_eq!(heap.len(), 5);
+                    assert_eq!(heap.into_sorted_vec(), [0, 3, 4, 6, 10]);
+                }
+
+                #[test]
+                fn compare_test() {
+                    let mut max = $heap::new_max();
+                    max.extend([3, 9, 1, 7]);
+                    assert_eq!(max.into_sorted_vec(), vec![9, 7, 3, 1]);
+
+                    let mut by_len = $heap::new_by_key(|s: &&str| s.len());
+                    by_len.extend(["ccc", "a", "bb"]);
+                    assert_eq!(by_len.delete_min(), Some("a"));
+
+                    let mut by = $heap::new_by(|a: &i32, b: &i32| (a % 10).cmp(&(b % 10)));
+                    by.extend([25, 31, 12]);
+                    assert_eq!(by.delete_min(), Some(31));
+
+                    let mut floats = $heap::with_compare(TotalOrder);
+                    floats.extend([2.5, f64::NAN, -1.0, 0.5]);
+                    assert_eq!(floats.delete_min(), Some(-1.0));
+                    assert_eq!(floats.delete_min(), Some(0.5));
+                    assert_eq!(floats.delete_min(), Some(2.5));
+                    assert!(floats.delete_min().unwrap().is_nan());
+                }
+
+                #[test]
+                fn map_test() {
+                    #[derive(Debug, PartialEq)]
+                    struct Job(&'static str);
+
+                    let mut heap = $heap::new_map();
+                    heap.insert((3, Job("c")));
+                    heap.insert((1, Job("a")));
+                    heap.insert((2, Job("b")));
+                    assert_eq!(heap.peek().map(|(k, _)| *k), Some(1));
+                    assert_eq!(heap.delete_min(), Some((1, Job("a"))));
+
+                    let mut max: $heap<(i32, Job), ByKey<MaxOrder>> =
+                        $heap::with_compare(ByKey(MaxOrder));
+                    max.extend(heap.drain());
+                    assert_eq!(max.delete_min(), Some((3, Job("c"))));
+                    assert_eq!(std::mem::size_of::<ByKey>(), 0);
+                }
+
+                #[test]
+                fn scope_test() {
+                    let mut heap = $heap::new();
+                    heap.extend([5, 8, 3]);
+                    let checked = heap.insert_with_handle(0);
+                    let len = heap.scope(|mut s| {
+                        assert_eq!(s.delete_min(), Some(0));
+                        let a = s.insert_with_handle(10);
+                        let b = s.insert_with_handle(20);
+                        s.decrease_key(b, 1).unwrap();
+                        assert_eq!(s.delete_min(), Some(1));
+                        assert_eq!(s.decrease_key(b, 0), Err(HeapError::StaleHandle));
+                        s.update(a, |v| v + 5).unwrap();
+                        s.increase_key(a, 16).unwrap();
+                        assert_eq!(s.peek(), Some(&3));
+                        assert_eq!(s.remove(a), Ok(16));
+                        assert_eq!(s.remove(a), Err(HeapError::StaleHandle));
+                        s.len()
+                    });
+                    assert_eq!(len, 3);
+                    assert_eq!(heap.decrease_key(&checked, 0), Err(HeapError::StaleHandle));
+                    assert_eq!(heap.into_sorted_vec(), vec![3, 5, 8]);
+                }
+
+                #[test]
+                fn retain_test() {
+                    let mut heap: $heap<i32> = (0..200).map(|i| i * 71 % 200).collect();
+                    let handles: Vec<_> = (200..210).map(|v| heap.insert_with_handle(v)).collect();
+                    for _ in 0..5 {
+                        heap.delete_min();
+                    }
+                    heap.retain(|&v| v % 3 != 0);
+                    let expected: Vec<i32> = (5..210).filter(|v| v % 3 != 0).collect();
+                    assert_eq!(heap.len(), expected.len());
+                    assert_eq!(heap.remove(handles[0].clone()), Ok(200));
+                    assert_eq!(heap.remove(handles[1].clone()), Err(HeapError::StaleHandle));
+
+                    let (small, large) = heap.partition(|&v| v < 100);
+                    assert_eq!(small.len() + large.len(), expected.len() - 1);
+                    assert!(large.iter().all(|&v| v >= 100));
+                    let mut small = small;
+                    assert_eq!(
+                        small.update(&handles[2], |v| v + 1),
+                        Err(HeapError::StaleHandle)
+                    );
+                    let mut large = large;
+                    large.insert(0);
+                    small.meld(large);
+                    let expected: Vec<i32> = std::iter::once(0)
+                        .chain(expected.into_iter().filter(|&v| v != 200))
+                        .collect();
+                    assert_eq!(small.clone().into_sorted_vec(), expected);
+
+                    // a panicking predicate leaves every element in the heap
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        small.retain(|&v| v < 150 || panic!())
+                    }));
+                    assert!(result.is_err());
+                    assert_eq!(small.len(), expected.len());
+                    assert_eq!(small.into_sorted_vec(), expected);
+                }
+
+                #[test]
+                fn pop_while_test() {
+                    let mut heap: $heap<i32> = (0..1000).map(|i| i * 7 % 1000).collect();
+                    let handles: Vec<_> = [40, 2000].map(|v| heap.insert_with_handle(v)).into();
+                    assert_eq!(heap.delete_min(), Some(0));
+                    let mut calls = 0;
+                    let due = heap.pop_while(|&v| {
+                        calls += 1;
+                        v <= 20
+                    });
+                    assert_eq!(due, (1..=20).collect::<Vec<_>>());
+                    // only the removed keys and their children are looked at, not the whole heap
+                    assert!(calls < 500, "{calls} calls");
+                    assert!(heap.pop_while(|&v| v < 21).is_empty());
+
+                    let below = heap.split_off_below(&50);
+                    assert_eq!(
+                        heap.update(&handles[0], |v| v - 1),
+                        Err(HeapError::StaleHandle)
+                    );
+                    assert_eq!(heap.update(&handles[1], |v| v + 1), Ok(()));
+                    let mut expected: Vec<i32> = (21..40).chain([40]).chain(40..50).collect();
+                    expected.sort();
+                    assert_eq!(below.len(), expected.len());
+                    assert_eq!(below.into_sorted_vec(), expected);
+                    assert_eq!(heap.len(), 951);
+                    assert_eq!(heap.peek(), Some(&50));
+                    let rest = heap.into_sorted_vec();
+                    assert_eq!(rest.last(), Some(&2001));
+                    assert!(rest.windows(2).all(|w| w[0] <= w[1]));
+                }
+
+                #[test]
+                fn iter_sorted_test() {
+                    let mut heap: $heap<i32, MaxOrder> = $heap::new_max();
+                    heap.extend((0..500).map(|i| i * 13 % 500));
+                    assert_eq!(heap.delete_min(), Some(499));
+                    heap.extend([600, 300]);
+                    let mut expected: Vec<i32> = (0..499).chain([600, 300]).collect();
+                    expected.sort_by(|a, b| b.cmp(a));
+                    assert_eq!(heap.iter_sorted().len(), expected.len());
+                    assert!(heap.iter_sorted().eq(&expected));
+                    assert_eq!(
+                        heap.k_smallest(5),
+                        expected.iter().take(5).collect::<Vec<_>>()
+                    );
+                    assert_eq!(heap.k_smallest(1000).len(), expected.len());
+                    // the heap is untouched
+                    assert_eq!(heap.len(), expected.len());
+                    assert_eq!(heap.into_sorted_vec(), expected);
+                    assert!($heap::<i32>::new().iter_sorted().next().is_none());
+                }
+
+                #[test]
+                fn map_keys_test() {
+                    let mut heap: $heap<i64> = (0..200).map(|i| i * 37 % 200).collect();
+                    let handle = heap.insert_with_handle(150);
+                    assert_eq!(heap.delete_min(), Some(0));
+                    heap.map_keys_monotone(|k| *k -= 1000);
+                    assert_eq!(heap.peek(), Some(&-999));
+                    heap.map_keys_monotone(|k| *k = *k * 3 + 1);
+                    assert_eq!(heap.peek(), Some(&-2996));
+                    assert_eq!(heap.remove(handle), Ok(-2549));
+                    let expected: Vec<i64> = (1..200).map(|k| (k - 1000) * 3 + 1).collect();
+                    assert_eq!(heap.clone().into_sorted_vec(), expected);
+
+                    // a panicking map leaves the order unspecified, but loses no element
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        heap.map_keys_monotone(|k| if *k < -2600 { *k += 1 } else { panic!() })
+                    }));
+                    assert!(result.is_err());
+                    assert_eq!(heap.len(), 199);
+                    assert_eq!(heap.iter().count(), 199);
+
+                    let mut max = $heap::new_by(|a: &f64, b: &f64| b.total_cmp(a));
+                    max.extend([1.5, -2.0, 4.25]);
+                    max.map_keys_monotone(|v| *v += 0.5);
+                    assert_eq!(max.into_sorted_vec(), [4.75, 2.0, -1.5]);
+                }
+            }
+        )*};
+    }
+
+    mod inherent {
+        inherent_tests!(pairing::PairingHeap, binomial::BinomialHeap);
+    }
+}
@@ -0,0 +1,68 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::{Heap, MeldableHeap};
+
+/// Min-heap adapter over `std::collections::BinaryHeap`.
+#[derive(Clone, Debug)]
+pub struct MinBinaryHeap<T> {
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord> MinBinaryHeap<T> {
+    pub const fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+        }
+    }
+}
+
+impl<T: Ord> Default for MinBinaryHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> Heap for MinBinaryHeap<T> {
+    type Item = T;
+
+    fn insert(&mut self, item: T) {
+        self.heap.push(Reverse(item));
+    }
+
+    fn delete_min(&mut self) -> Option<T> {
+        self.heap.pop().map(|Reverse(item)| item)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.heap.peek().map(|Reverse(item)| item)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+impl<T: Ord> MeldableHeap for MinBinaryHeap<T> {
+    fn meld(&mut self, mut other: Self) {
+        self.heap.append(&mut other.heap);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn min_order() {
+        let mut heap = MinBinaryHeap::new();
+        for i in [5, 1, 4, 2, 3] {
+            heap.insert(i);
+        }
+        let v: Vec<i32> = (0..heap.len())
+            .map(|_| heap.delete_min().unwrap())
+            .collect();
+        assert_eq!(v, vec![1, 2, 3, 4, 5]);
+        assert!(heap.is_empty());
+    }
+}
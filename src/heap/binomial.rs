@@ -2,24 +2,80 @@
 
 use std::alloc::Layout;
 use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
 // use std::mem::{ManuallyDrop, MaybeUninit};
 use std::ptr::NonNull;
 // use std::rc::Rc;
 
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::hash::{Hash, Hasher};
+
+use super::compare::{ByKey, Compare, FnCompare, KeyCompare, MaxOrder, MinOrder};
+use super::handle::{Brand, Identity, Slot, Ticket};
+use super::pairing::PairingHeap;
+use super::{AddressableHeap, Heap, HeapError, MeldableHeap, Recycled};
+
 struct Node<K> {
     next: Option<NonNull<Node<K>>>,
     prev: Option<NonNull<Node<K>>>,
     child: Option<NonNull<Node<K>>>,
+    parent: Option<NonNull<Node<K>>>,
+    /// Set only for nodes inserted with a handle.
+    slot: Option<Slot<Node<K>>>,
     degree: usize,
     key: K,
 }
 
-pub struct BinomialHeap<K> {
+type NodePtr<K> = NonNull<Node<K>>;
+
+pub struct BinomialHeap<K, C = MinOrder> {
     min: Option<NonNull<Node<K>>>,
     size: usize,
+    cmp: C,
+    id: Identity,
     marker: PhantomData<Box<Node<K>>>,
 }
 
+/// Heap of `(key, value)` pairs ordered by the key alone, so `V` need not be comparable.
+///
+/// `delete_min` returns the whole pair. Create one with [`BinomialHeap::new_map`] or
+/// `BinomialHeap::with_compare(ByKey(cmp))`.
+pub type BinomialMap<K, V, C = MinOrder> = BinomialHeap<(K, V), ByKey<C>>;
+
+/// Points to an element inserted with [`BinomialHeap::insert_with_handle`].
+///
+/// Its slot moves along with the key when `decrease_key` swaps keys between nodes. Uses are
+/// checked as described on [`AddressableHeap`].
+pub struct Handle<K>(Ticket<Node<K>>);
+
+impl<K> Clone for Handle<K> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K> PartialEq for Handle<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K> Eq for Handle<K> {}
+
+impl<K> Debug for Handle<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.0).finish()
+    }
+}
+
+/// Guard returned by [`BinomialHeap::peek_mut`].
+pub struct PeekMut<'a, K, C: Compare<K> = MinOrder> {
+    heap: &'a mut BinomialHeap<K, C>,
+    modified: bool,
+}
+
 impl<K> Node<K> {
     const fn new(key: K) -> Self {
         Self {
@@ -27,6 +83,8 @@ impl<K> Node<K> {
             next: None,
             prev: None,
             child: None,
+            parent: None,
+            slot: None,
             degree: 0,
         }
     }
@@ -50,6 +108,8 @@ impl<K> Node<K> {
                 next: nn,
                 prev: nn,
                 child: None,
+                parent: None,
+                slot: None,
                 degree: 0,
             });
             // (*p.as_ptr()).next = Some(p);
@@ -58,183 +118,1324 @@ impl<K> Node<K> {
         }
     }
 
-    fn unlink(mut node: NonNull<Self>) -> NonNull<Self> {
-        unsafe {
-            if let Some(mut ne) = node.as_ref().next {
-                node.as_mut().next = None;
-                ne.as_mut().prev = None;
-            }
-            node
-        }
+    fn unlink(mut node: NonNull<Self>) -> NonNull<Self> {
+        unsafe {
+            if let Some(mut ne) = node.as_ref().next {
+                node.as_mut().next = None;
+                ne.as_mut().prev = None;
+            }
+            node
+        }
+    }
+
+    /// nodeを指すポインタが残っていてはならない
+    fn into_key(node: NonNull<Self>) -> K {
+        unsafe {
+            let n = std::ptr::read(node.as_ptr() as *const Self);
+            std::alloc::dealloc(node.as_ptr() as *mut _, Layout::new::<Self>());
+            n.key
+        }
+    }
+}
+
+impl<K> BinomialHeap<K> {
+    pub const fn new() -> Self {
+        Self::with_compare(MinOrder)
+    }
+}
+
+impl<K: Ord, V> BinomialHeap<(K, V), ByKey> {
+    /// Creates a key-value heap that serves the pair with the smallest key first.
+    pub const fn new_map() -> Self {
+        Self::with_compare(ByKey(MinOrder))
+    }
+}
+
+impl<K> BinomialHeap<K, MaxOrder> {
+    /// Creates a heap that serves the largest key first.
+    pub const fn new_max() -> Self {
+        Self::with_compare(MaxOrder)
+    }
+}
+
+impl<K, F: Fn(&K, &K) -> std::cmp::Ordering> BinomialHeap<K, FnCompare<F>> {
+    /// Creates a heap ordered by `f`; the key that compares `Less` is served first.
+    pub const fn new_by(f: F) -> Self {
+        Self::with_compare(FnCompare(f))
+    }
+}
+
+impl<K, T: Ord, F: Fn(&K) -> T> BinomialHeap<K, KeyCompare<F>> {
+    /// Creates a heap that serves the key with the smallest `f(&key)` first.
+    pub const fn new_by_key(f: F) -> Self {
+        Self::with_compare(KeyCompare(f))
+    }
+}
+
+impl<K, C> BinomialHeap<K, C> {
+    pub const fn with_compare(cmp: C) -> Self {
+        Self {
+            min: None,
+            size: 0,
+            cmp,
+            id: Identity::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn compare(&self) -> &C {
+        &self.cmp
+    }
+
+    pub const fn len(&self) -> usize {
+        self.size
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+impl<K, C: Default> Default for BinomialHeap<K, C> {
+    fn default() -> Self {
+        Self::with_compare(C::default())
+    }
+}
+
+impl<K, C: Compare<K>> BinomialHeap<K, C> {
+    fn merge(cmp: &C, mut root: NonNull<Node<K>>, mut other: NonNull<Node<K>>) -> NonNull<Node<K>> {
+        unsafe {
+            if cmp.gt(&root.as_ref().key, &other.as_ref().key) {
+                if let Some(och) = other.as_ref().child {
+                    (*other.as_ptr()).child = Some(Self::insert_node(cmp, och, root));
+                } else {
+                    root.as_mut().prev = Some(root);
+                    root.as_mut().next = Some(root);
+                    other.as_mut().child = Some(root);
+                }
+                root.as_mut().parent = Some(other);
+                other.as_mut().degree += 1;
+                other
+            } else {
+                if let Some(rch) = root.as_ref().child {
+                    (*root.as_ptr()).child = Some(Self::insert_node(cmp, rch, other));
+                } else {
+                    other.as_mut().prev = Some(other);
+                    other.as_mut().next = Some(other);
+                    root.as_mut().child = Some(other);
+                }
+                other.as_mut().parent = Some(root);
+                root.as_mut().degree += 1;
+                root
+            }
+        }
+    }
+
+    fn insert_node(cmp: &C, min: NonNull<Node<K>>, node: NonNull<Node<K>>) -> NonNull<Node<K>> {
+        unsafe {
+            Self::insert_node_next(min, node);
+            if cmp.gt(&(*min.as_ptr()).key, &(*node.as_ptr()).key) {
+                node
+            } else {
+                min
+            }
+        }
+    }
+
+    fn insert_node_prev(ptr: NonNull<Node<K>>, node: NonNull<Node<K>>) {
+        unsafe {
+            let prev = ptr.as_ref().prev.unwrap();
+            (*prev.as_ptr()).next = Some(node);
+            (*ptr.as_ptr()).prev = Some(node);
+            (*node.as_ptr()).next = Some(ptr);
+            (*node.as_ptr()).prev = Some(prev);
+        }
+    }
+
+    fn insert_node_next(ptr: NonNull<Node<K>>, node: NonNull<Node<K>>) {
+        unsafe {
+            let next = ptr.as_ref().next.unwrap();
+            (*next.as_ptr()).prev = Some(node);
+            (*ptr.as_ptr()).next = Some(node);
+            (*node.as_ptr()).prev = Some(ptr);
+            (*node.as_ptr()).next = Some(next);
+        }
+    }
+
+    pub fn insert(&mut self, key: K) {
+        self.push_node(Node::new_ptr(key));
+    }
+
+    /// Inserts `key` and returns a handle to it for [`decrease_key`](Self::decrease_key).
+    pub fn insert_with_handle(&mut self, key: K) -> Handle<K> {
+        let node = Node::new_ptr(key);
+        let (slot, ticket) = Ticket::issue(&mut self.id, node);
+        unsafe { (*node.as_ptr()).slot = Some(slot) };
+        self.push_node(node);
+        Handle(ticket)
+    }
+
+    /// Replaces the key of `handle` with `key`, which must not be greater.
+    ///
+    /// The key bubbles up its tree by swapping with its parents, in O(log n). Slots of the
+    /// swapped keys follow them, so every handle keeps pointing to its element.
+    ///
+    /// # Panics
+    /// Panics if `key` is greater than the current key.
+    pub fn decrease_key(&mut self, handle: &Handle<K>, key: K) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.decrease_node(node, key, false) };
+        Ok(())
+    }
+
+    /// Removes the element of `handle` and returns it.
+    ///
+    /// The element is moved up to the root of its tree, which is then removed like the minimum,
+    /// in O(log n) amortized.
+    pub fn remove(&mut self, handle: Handle<K>) -> Result<K, HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { Ok(Node::into_key(self.remove_node(node, false))) }
+    }
+
+    /// Replaces the key of `handle` with `key`, which must not be less.
+    ///
+    /// The element is moved up to the root of its tree, whose children are melded into the root
+    /// list like in `delete_min`, and its node is inserted back with the new key, in O(log n)
+    /// amortized.
+    ///
+    /// # Panics
+    /// Panics if `key` is less than the current key.
+    pub fn increase_key(&mut self, handle: &Handle<K>, key: K) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.increase_node(node, key, false) };
+        Ok(())
+    }
+
+    /// Replaces the key of `handle` with `f(key)`, calling [`decrease_key`](Self::decrease_key)
+    /// or [`increase_key`](Self::increase_key) depending on the direction of the change.
+    pub fn update<F: FnOnce(&K) -> K>(
+        &mut self,
+        handle: &Handle<K>,
+        f: F,
+    ) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.update_node(node, f, false) };
+        Ok(())
+    }
+
+    unsafe fn decrease_node(&mut self, node: NodePtr<K>, key: K, relink: bool) {
+        assert!(
+            !self.cmp.gt(&key, &(*node.as_ptr()).key),
+            "decrease_key: new key is greater than the current one"
+        );
+        (*node.as_ptr()).key = key;
+        let node = self.bubble_up(node, false, relink);
+        let min = self.min.unwrap();
+        if (*node.as_ptr()).parent.is_none()
+            && self.cmp.lt(&(*node.as_ptr()).key, &(*min.as_ptr()).key)
+        {
+            self.min = Some(node);
+        }
+    }
+
+    unsafe fn increase_node(&mut self, node: NodePtr<K>, key: K, relink: bool) {
+        assert!(
+            !self.cmp.lt(&key, &(*node.as_ptr()).key),
+            "increase_key: new key is less than the current one"
+        );
+        let node = self.remove_node(node, relink);
+        (*node.as_ptr()).key = key;
+        self.push_node(node);
+    }
+
+    unsafe fn update_node<F: FnOnce(&K) -> K>(&mut self, node: NodePtr<K>, f: F, relink: bool) {
+        let key = f(&(*node.as_ptr()).key);
+        match self.cmp.compare(&key, &(*node.as_ptr()).key) {
+            Ordering::Less => self.decrease_node(node, key, relink),
+            Ordering::Greater => self.increase_node(node, key, relink),
+            Ordering::Equal => (*node.as_ptr()).key = key,
+        }
+    }
+
+    /// Detaches the element of `node` from the heap and returns the node now holding it.
+    unsafe fn remove_node(&mut self, node: NodePtr<K>, relink: bool) -> NodePtr<K> {
+        let node = self.bubble_up(node, true, relink);
+        self.pop_root(node)
+    }
+
+    /// Swaps the key of `node` with its parents while it is smaller, or all the way up to the
+    /// root if `to_root` is set, and returns the node it ends up in. Slots follow their keys.
+    ///
+    /// With `relink` the nodes trade places instead of keys, so the element never leaves `node`,
+    /// at the cost of re-parenting both child lists on every step.
+    unsafe fn bubble_up(
+        &mut self,
+        mut node: NodePtr<K>,
+        to_root: bool,
+        relink: bool,
+    ) -> NodePtr<K> {
+        while let Some(parent) = (*node.as_ptr()).parent {
+            if !to_root && !self.cmp.lt(&(*node.as_ptr()).key, &(*parent.as_ptr()).key) {
+                break;
+            }
+            if relink {
+                self.swap_with_parent(node, parent);
+                continue;
+            }
+            std::ptr::swap(&mut (*node.as_ptr()).key, &mut (*parent.as_ptr()).key);
+            std::ptr::swap(&mut (*node.as_ptr()).slot, &mut (*parent.as_ptr()).slot);
+            for n in [node, parent] {
+                if let Some(slot) = &(*n.as_ptr()).slot {
+                    slot.set(n);
+                }
+            }
+            node = parent;
+        }
+        node
+    }
+
+    /// Makes `node` take the place of its parent `parent` in the tree and vice versa.
+    unsafe fn swap_with_parent(&mut self, node: NodePtr<K>, parent: NodePtr<K>) {
+        /// Puts `new` where `old` was in a cyclic list whose neighbours of `old` are `prev` and
+        /// `next`.
+        unsafe fn replace<K>(old: NodePtr<K>, new: NodePtr<K>, prev: NodePtr<K>, next: NodePtr<K>) {
+            if next == old {
+                (*new.as_ptr()).next = Some(new);
+                (*new.as_ptr()).prev = Some(new);
+            } else {
+                (*new.as_ptr()).next = Some(next);
+                (*new.as_ptr()).prev = Some(prev);
+                (*prev.as_ptr()).next = Some(new);
+                (*next.as_ptr()).prev = Some(new);
+            }
+        }
+        /// Sets the parent of every node in the cyclic list of `first`.
+        unsafe fn adopt<K>(first: Option<NodePtr<K>>, parent: NodePtr<K>) {
+            if let Some(first) = first {
+                let mut p = first;
+                loop {
+                    (*p.as_ptr()).parent = Some(parent);
+                    p = (*p.as_ptr()).next.unwrap();
+                    if p == first {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let (n, p) = (node.as_ptr(), parent.as_ptr());
+        let (node_prev, node_next) = ((*n).prev.unwrap(), (*n).next.unwrap());
+        let (parent_prev, parent_next) = ((*p).prev.unwrap(), (*p).next.unwrap());
+        replace(node, parent, node_prev, node_next);
+        replace(parent, node, parent_prev, parent_next);
+        let grandparent = (*p).parent;
+        if let Some(gp) = grandparent {
+            if (*gp.as_ptr()).child == Some(parent) {
+                (*gp.as_ptr()).child = Some(node);
+            }
+        } else if self.min == Some(parent) {
+            self.min = Some(node);
+        }
+        let children = (*n).child;
+        (*n).child = if (*p).child == Some(node) {
+            Some(parent)
+        } else {
+            (*p).child
+        };
+        (*p).child = children;
+        adopt((*n).child, node);
+        adopt(children, parent);
+        (*n).parent = grandparent;
+        std::ptr::swap(&mut (*n).degree, &mut (*p).degree);
+    }
+
+    /// Adds a detached node to the root list.
+    fn push_node(&mut self, node: NonNull<Node<K>>) {
+        if let Some(nn) = self.min {
+            unsafe {
+                Self::insert_node_prev(nn, node);
+                if self.cmp.gt(&nn.as_ref().key, &node.as_ref().key) {
+                    self.min = Some(node);
+                }
+            }
+        } else {
+            unsafe {
+                (*node.as_ptr()).next = Some(node);
+                (*node.as_ptr()).prev = Some(node);
+            }
+            self.min = Some(node);
+        }
+        self.size += 1;
+    }
+
+    /// Returns node whose key is minimum
+    fn merge_list(
+        cmp: &C,
+        head: NonNull<Node<K>>,
+        head2: Option<NonNull<Node<K>>>,
+        size: usize,
+    ) -> NonNull<Node<K>> {
+        let mut v = vec![None; size.ilog2() as usize + 1];
+        let mut p = Some(head);
+        unsafe {
+            while let Some(mut ne) = p {
+                p = (*ne.as_ptr()).next;
+                while let Some(other) = v[(*ne.as_ptr()).degree].take() {
+                    ne = Self::merge(cmp, ne, other);
+                }
+                v[(*ne.as_ptr()).degree] = Some(ne);
+                if p == Some(head) {
+                    break;
+                }
+            }
+            p = head2;
+            while let Some(mut ne) = p {
+                p = (*ne.as_ptr()).next;
+                (*ne.as_ptr()).parent = None;
+                while let Some(other) = v[(*ne.as_ptr()).degree].take() {
+                    ne = Self::merge(cmp, ne, other);
+                }
+                v[(*ne.as_ptr()).degree] = Some(ne);
+                if p == head2 {
+                    break;
+                }
+            }
+            let mut itr = v.into_iter().flatten();
+            let head = itr.next().unwrap();
+            let mut min = head;
+            let mut p = head;
+            for item in itr {
+                (*p.as_ptr()).next = Some(item);
+                (*item.as_ptr()).prev = Some(p);
+                p = item;
+                if cmp.gt(&(*min.as_ptr()).key, &(*item.as_ptr()).key) {
+                    min = item;
+                }
+            }
+            (*p.as_ptr()).next = Some(head);
+            (*head.as_ptr()).prev = Some(p);
+            min
+        }
+    }
+
+    pub fn delete_min(&mut self) -> Option<K> {
+        self.pop_node().map(Node::into_key)
+    }
+
+    /// Detaches the minimum node and returns it with all links cleared.
+    fn pop_node(&mut self) -> Option<NonNull<Node<K>>> {
+        let min = self.min?;
+        Some(unsafe { self.pop_root(min) })
+    }
+
+    /// Detaches `root` from the root list and returns it with all links cleared.
+    ///
+    /// `root` need not be the minimum; the minimum is searched again among the remaining roots.
+    unsafe fn pop_root(&mut self, root: NodePtr<K>) -> NodePtr<K> {
+        let prev = (*root.as_ptr()).prev.unwrap();
+        if prev == root {
+            // `child` may not be the minimum child after `decrease_key`, so look for it
+            self.min = (*root.as_ptr()).child;
+            if let Some(first) = self.min {
+                let mut p = first;
+                loop {
+                    (*p.as_ptr()).parent = None;
+                    if self
+                        .cmp
+                        .lt(&(*p.as_ptr()).key, &(*self.min.unwrap().as_ptr()).key)
+                    {
+                        self.min = Some(p);
+                    }
+                    p = (*p.as_ptr()).next.unwrap();
+                    if p == first {
+                        break;
+                    }
+                }
+            }
+        } else {
+            (*prev.as_ptr()).next = None;
+            self.min = Some(Self::merge_list(
+                &self.cmp,
+                (*root.as_ptr()).next.unwrap(),
+                (*root.as_ptr()).child,
+                self.size,
+            ));
+        }
+        let node = &mut *root.as_ptr();
+        node.next = None;
+        node.prev = None;
+        node.child = None;
+        node.degree = 0;
+        self.size -= 1;
+        root
+    }
+
+    pub fn peek(&self) -> Option<&K> {
+        self.min.map(|min| unsafe { &(*min.as_ptr()).key })
+    }
+
+    /// Returns a guard that gives mutable access to the minimum.
+    ///
+    /// The heap order is restored when the guard is dropped.
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, K, C>> {
+        if self.min.is_some() {
+            Some(PeekMut {
+                heap: self,
+                modified: false,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Re-inserts the minimum node after its key may have grown.
+    fn sift_min(&mut self) {
+        if let Some(node) = self.pop_node() {
+            self.push_node(node);
+        }
+    }
+
+    /// Inserts `key` and then removes the minimum.
+    ///
+    /// Returns `key` right away if it is not greater than the current minimum.
+    pub fn push_pop(&mut self, key: K) -> K {
+        match self.peek() {
+            Some(min) if self.cmp.lt(min, &key) => self.replace_min(key).unwrap(),
+            _ => key,
+        }
+    }
+
+    /// Removes the minimum and inserts `key`, reusing the node of the removed element.
+    ///
+    /// Returns `None` and only inserts `key` if the heap is empty.
+    pub fn replace_min(&mut self, key: K) -> Option<K> {
+        if let Some(min) = self.min {
+            let old = unsafe { std::mem::replace(&mut (*min.as_ptr()).key, key) };
+            // handles of the old key must not reach the new one
+            unsafe { (*min.as_ptr()).slot = None };
+            if unsafe { self.cmp.gt(&(*min.as_ptr()).key, &old) } {
+                self.sift_min();
+            }
+            Some(old)
+        } else {
+            self.insert(key);
+            None
+        }
+    }
+
+    /// Moves all elements of `other` into `self` in O(1).
+    ///
+    /// The root lists are only spliced together; consolidation is deferred to the next `delete_min`.
+    pub fn meld(&mut self, mut other: Self) {
+        self.append(&mut other);
+    }
+
+    /// Moves all elements of `other` into `self` in O(1), leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        if let Some(b) = other.min.take() {
+            self.splice(b);
+            self.size += std::mem::take(&mut other.size);
+        }
+        self.id.absorb(&mut other.id);
+    }
+
+    /// Splices the root list containing `b` into the root list of `self`.
+    fn splice(&mut self, b: NonNull<Node<K>>) {
+        if let Some(a) = self.min {
+            unsafe {
+                let a_prev = (*a.as_ptr()).prev.unwrap();
+                let b_prev = (*b.as_ptr()).prev.unwrap();
+                (*a_prev.as_ptr()).next = Some(b);
+                (*b.as_ptr()).prev = Some(a_prev);
+                (*b_prev.as_ptr()).next = Some(a);
+                (*a.as_ptr()).prev = Some(b_prev);
+                if self.cmp.gt(&(*a.as_ptr()).key, &(*b.as_ptr()).key) {
+                    self.min = Some(b);
+                }
+            }
+        } else {
+            self.min = Some(b);
+        }
+    }
+
+    /// Melds `other` into `self` and links trees of equal degree right away.
+    ///
+    /// Runs in O(log n) when neither heap has pending lazy insertions.
+    pub fn meld_eager(&mut self, other: Self) {
+        self.meld(other);
+        self.consolidate();
+    }
+
+    /// Links trees of equal degree until every root has a distinct degree.
+    pub fn consolidate(&mut self) {
+        if let Some(min) = self.min {
+            unsafe {
+                let prev = (*min.as_ptr()).prev.unwrap();
+                (*prev.as_ptr()).next = None;
+                self.min = Some(Self::merge_list(&self.cmp, min, None, self.size));
+            }
+        }
+    }
+
+    /// Melds every heap yielded by `heaps` into one in time linear in the number of heaps,
+    /// ordered by `C::default()`.
+    pub fn meld_all<I: IntoIterator<Item = Self>>(heaps: I) -> Self
+    where
+        C: Default,
+    {
+        let mut heap = Self::default();
+        for other in heaps {
+            heap.meld(other);
+        }
+        heap
+    }
+
+    /// Keeps only the elements for which `pred` returns `true`.
+    ///
+    /// Every node is visited once and the survivors are linked into a new binomial forest, in
+    /// O(n). Handles to the removed elements become stale.
+    pub fn retain<F: FnMut(&K) -> bool>(&mut self, pred: F) {
+        let (rejected, len) = self.sift(pred);
+        drop(IntoIter {
+            head: rejected,
+            len,
+            marker: PhantomData,
+        });
+    }
+
+    /// Splits the heap into the elements for which `pred` returns `true` and the others, in O(n)
+    /// like [`retain`](Self::retain).
+    ///
+    /// Handles keep working on the first heap; handles to the elements of the second one become
+    /// stale.
+    pub fn partition<F: FnMut(&K) -> bool>(mut self, pred: F) -> (Self, Self)
+    where
+        C: Clone,
+    {
+        let (rejected, size) = self.sift(pred);
+        let mut other = Self::with_compare(self.cmp.clone());
+        if let Some(head) = rejected {
+            let mut p = rejected;
+            while let Some(node) = p {
+                unsafe {
+                    (*node.as_ptr()).slot = None;
+                    p = (*node.as_ptr()).next;
+                }
+            }
+            other.min = Some(Self::merge_list(&other.cmp, head, None, size));
+            other.size = size;
+        }
+        (self, other)
+    }
+
+    /// Removes the keys for which `pred` returns `true` and returns them in heap order.
+    ///
+    /// `pred` must hold for a prefix of the heap order, like `|&t| t <= now`. Subtrees are pruned
+    /// at the first key that fails it, so only the roots, the removed keys and their children are
+    /// visited, and the roots are consolidated like in `delete_min`. This takes amortized
+    /// O(k log n) for k removed keys.
+    pub fn pop_while<F: FnMut(&K) -> bool>(&mut self, pred: F) -> Vec<K> {
+        let mut keys: Vec<K> = self
+            .take_while_nodes(pred)
+            .into_iter()
+            .map(Node::into_key)
+            .collect();
+        keys.sort_by(|a, b| self.cmp.compare(a, b));
+        keys
+    }
+
+    /// Moves the keys less than `bound` into a new heap, pruning subtrees like
+    /// [`pop_while`](Self::pop_while).
+    ///
+    /// Handles to the moved keys become stale.
+    pub fn split_off_below(&mut self, bound: &K) -> Self
+    where
+        C: Clone,
+    {
+        let cmp = self.cmp.clone();
+        let nodes = self.take_while_nodes(|key| cmp.lt(key, bound));
+        let mut other = Self::with_compare(cmp);
+        let mut head = None;
+        for &node in &nodes {
+            unsafe { (*node.as_ptr()).next = head };
+            head = Some(node);
+        }
+        if let Some(head) = head {
+            other.min = Some(Self::merge_list(&other.cmp, head, None, nodes.len()));
+            other.size = nodes.len();
+        }
+        other
+    }
+
+    /// Detaches the nodes reached from the roots through keys satisfying `pred`, with all links
+    /// and slots cleared.
+    ///
+    /// The roots and children left behind are linked back into the heap by degree. The walk only
+    /// reads links, so if `pred` panics, `min`, `size` and every tree are as they were.
+    fn take_while_nodes<F: FnMut(&K) -> bool>(&mut self, mut pred: F) -> Vec<NodePtr<K>> {
+        let mut taken = Vec::new();
+        // every key is at least the minimum, so nothing passes if it fails
+        let Some(min) = self.min.filter(|min| pred(unsafe { &(*min.as_ptr()).key })) else {
+            return taken;
+        };
+        let mut stack = Vec::new();
+        let mut orphans = Vec::new();
+        let mut visit = |first: NodePtr<K>, stack: &mut Vec<_>| {
+            let mut p = first;
+            loop {
+                if p == min || pred(unsafe { &(*p.as_ptr()).key }) {
+                    stack.push(p);
+                } else {
+                    orphans.push(p);
+                }
+                p = unsafe { (*p.as_ptr()).next.unwrap() };
+                if p == first {
+                    break;
+                }
+            }
+        };
+        visit(min, &mut stack);
+        while let Some(node) = stack.pop() {
+            taken.push(node);
+            if let Some(child) = unsafe { (*node.as_ptr()).child } {
+                visit(child, &mut stack);
+            }
+        }
+        let mut rest = None;
+        for node in orphans {
+            unsafe {
+                (*node.as_ptr()).next = rest;
+                (*node.as_ptr()).parent = None;
+            }
+            rest = Some(node);
+        }
+        for &node in &taken {
+            let n = unsafe { &mut *node.as_ptr() };
+            n.next = None;
+            n.prev = None;
+            n.child = None;
+            n.parent = None;
+            n.degree = 0;
+            n.slot = None;
+        }
+        self.size -= taken.len();
+        self.min = rest.map(|head| Self::merge_list(&self.cmp, head, None, self.size));
+        taken
+    }
+
+    /// Unlinks the keys for which `pred` returns `false` and returns them as a chain, with their
+    /// number. The others are linked back into the heap.
+    fn sift<F: FnMut(&K) -> bool>(&mut self, mut pred: F) -> (Option<NodePtr<K>>, usize) {
+        let rest = self.min.take().inspect(|&min| unsafe {
+            let prev = (*min.as_ptr()).prev.unwrap();
+            (*prev.as_ptr()).next = None;
+        });
+        let mut sieve = Sieve {
+            heap: self,
+            rest,
+            kept: None,
+            kept_len: 0,
+            rejected: None,
+        };
+        let mut count = 0;
+        while let Some(node) = sieve.pop() {
+            unsafe { (*node.as_ptr()).next = sieve.kept };
+            sieve.kept = Some(node);
+            sieve.kept_len += 1;
+            if !pred(unsafe { &(*node.as_ptr()).key }) {
+                unsafe {
+                    sieve.kept = (*node.as_ptr()).next;
+                    (*node.as_ptr()).next = sieve.rejected;
+                }
+                sieve.rejected = Some(node);
+                sieve.kept_len -= 1;
+                count += 1;
+            }
+        }
+        sieve.heap.size -= count;
+        (sieve.rejected.take(), count)
+    }
+}
+
+/// Nodes of a heap being sifted by `retain` or `partition`, linked back into the heap on drop.
+///
+/// While sifting, `heap.min` is `None` and every node is in one of `rest`, `kept` and
+/// `rejected`, with `kept_len` counting `kept`. A node joins `kept` before the predicate sees it,
+/// so if the predicate panics, the drop moves `rest` and `rejected` into `kept` and links all of
+/// it by degree, leaving `heap.size` as it was.
+struct Sieve<'a, K, C: Compare<K>> {
+    heap: &'a mut BinomialHeap<K, C>,
+    /// Chain of the trees not visited yet.
+    rest: Option<NodePtr<K>>,
+    kept: Option<NodePtr<K>>,
+    kept_len: usize,
+    rejected: Option<NodePtr<K>>,
+}
+
+impl<K, C: Compare<K>> Sieve<'_, K, C> {
+    /// Detaches the next node of `rest` with all links cleared.
+    fn pop(&mut self) -> Option<NodePtr<K>> {
+        let node = Node::pop_front(&mut self.rest)?;
+        unsafe {
+            let n = &mut *node.as_ptr();
+            n.next = None;
+            n.prev = None;
+            n.parent = None;
+            n.degree = 0;
+        }
+        Some(node)
+    }
+}
+
+impl<K, C: Compare<K>> Drop for Sieve<'_, K, C> {
+    fn drop(&mut self) {
+        while let Some(node) = self.pop().or_else(|| Node::pop_front(&mut self.rejected)) {
+            unsafe { (*node.as_ptr()).next = self.kept };
+            self.kept = Some(node);
+            self.kept_len += 1;
+        }
+        if let Some(head) = self.kept.take() {
+            self.heap.min = Some(BinomialHeap::merge_list(
+                &self.heap.cmp,
+                head,
+                None,
+                self.kept_len,
+            ));
+        }
+    }
+}
+
+impl<K, C: Compare<K>> Heap for BinomialHeap<K, C> {
+    type Item = K;
+
+    fn insert(&mut self, item: K) {
+        Self::insert(self, item);
+    }
+
+    fn delete_min(&mut self) -> Option<K> {
+        Self::delete_min(self)
+    }
+
+    fn peek(&self) -> Option<&K> {
+        Self::peek(self)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+impl<K, C: Compare<K>> AddressableHeap for BinomialHeap<K, C> {
+    type Handle = Handle<K>;
+
+    fn insert_with_handle(&mut self, item: K) -> Handle<K> {
+        Self::insert_with_handle(self, item)
+    }
+
+    fn decrease_key(&mut self, handle: &Handle<K>, item: K) -> Result<(), HeapError> {
+        Self::decrease_key(self, handle, item)
+    }
+
+    fn remove(&mut self, handle: Handle<K>) -> Result<K, HeapError> {
+        Self::remove(self, handle)
+    }
+
+    fn increase_key(&mut self, handle: &Handle<K>, item: K) -> Result<(), HeapError> {
+        Self::increase_key(self, handle, item)
+    }
+
+    fn update<F: FnOnce(&K) -> K>(&mut self, handle: &Handle<K>, f: F) -> Result<(), HeapError> {
+        Self::update(self, handle, f)
+    }
+}
+
+impl<K, C: Compare<K>> MeldableHeap for BinomialHeap<K, C> {
+    fn meld(&mut self, other: Self) {
+        Self::meld(self, other);
+    }
+}
+
+/// Handle to an element inserted through a [`Scope`], usable only within that scope.
+pub struct BrandedHandle<'id, K> {
+    node: NodePtr<K>,
+    brand: Brand<'id>,
+}
+
+impl<K> Clone for BrandedHandle<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for BrandedHandle<'_, K> {}
+
+impl<K> PartialEq for BrandedHandle<'_, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<K> Eq for BrandedHandle<'_, K> {}
+
+impl<K> Debug for BrandedHandle<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BrandedHandle").field(&self.node).finish()
+    }
+}
+
+/// View of a [`BinomialHeap`] whose handles are checked at compile time, created by
+/// [`BinomialHeap::scope`].
+pub struct Scope<'id, 'a, K, C = MinOrder> {
+    heap: &'a mut BinomialHeap<K, C>,
+    /// Nodes removed in this scope, chained by `next` and freed when the scope ends.
+    retired: Option<NodePtr<K>>,
+    brand: Brand<'id>,
+}
+
+impl<K, C: Compare<K>> BinomialHeap<K, C> {
+    /// Calls `f` with a [`Scope`] whose handles are raw node pointers branded with a lifetime
+    /// unique to this call.
+    ///
+    /// The handles cannot leave `f` or be passed to another scope, so unlike [`Handle`] they carry
+    /// no heap id. Inside the scope nodes trade places with their parents instead of keys, so an
+    /// element never leaves its node; this makes `decrease_key` O(log² n) in the worst case.
+    /// Nodes removed inside the scope stay allocated until it ends and are marked in place, so a
+    /// handle to a removed element still fails with [`HeapError::StaleHandle`].
+    ///
+    /// ```compile_fail
+    /// # use heaps::heap::binomial::BinomialHeap;
+    /// let mut heap = BinomialHeap::<i32>::new();
+    /// let h = heap.scope(|mut s| s.insert_with_handle(1));
+    /// ```
+    pub fn scope<'a, R, F>(&'a mut self, f: F) -> R
+    where
+        F: for<'id> FnOnce(Scope<'id, 'a, K, C>) -> R,
+    {
+        f(Scope {
+            heap: self,
+            retired: None,
+            brand: PhantomData,
+        })
+    }
+}
+
+impl<'id, K, C: Compare<K>> Scope<'id, '_, K, C> {
+    pub fn insert(&mut self, key: K) {
+        self.heap.insert(key);
+    }
+
+    pub fn insert_with_handle(&mut self, key: K) -> BrandedHandle<'id, K> {
+        let node = Node::new_ptr(key);
+        self.heap.push_node(node);
+        BrandedHandle {
+            node,
+            brand: PhantomData,
+        }
+    }
+
+    /// Same as [`BinomialHeap::decrease_key`], but moves the node instead of the key.
+    pub fn decrease_key(&mut self, handle: BrandedHandle<'id, K>, key: K) -> Result<(), HeapError> {
+        let node = self.node(handle)?;
+        unsafe { self.heap.decrease_node(node, key, true) };
+        Ok(())
+    }
+
+    /// Same as [`BinomialHeap::increase_key`].
+    pub fn increase_key(&mut self, handle: BrandedHandle<'id, K>, key: K) -> Result<(), HeapError> {
+        let node = self.node(handle)?;
+        unsafe { self.heap.increase_node(node, key, true) };
+        Ok(())
+    }
+
+    /// Same as [`BinomialHeap::update`].
+    pub fn update<F: FnOnce(&K) -> K>(
+        &mut self,
+        handle: BrandedHandle<'id, K>,
+        f: F,
+    ) -> Result<(), HeapError> {
+        let node = self.node(handle)?;
+        unsafe { self.heap.update_node(node, f, true) };
+        Ok(())
+    }
+
+    /// Same as [`BinomialHeap::remove`].
+    pub fn remove(&mut self, handle: BrandedHandle<'id, K>) -> Result<K, HeapError> {
+        let node = self.node(handle)?;
+        unsafe {
+            let node = self.heap.remove_node(node, true);
+            Ok(self.retire(node))
+        }
+    }
+
+    pub fn delete_min(&mut self) -> Option<K> {
+        let node = self.heap.pop_node()?;
+        Some(unsafe { self.retire(node) })
+    }
+
+    pub fn peek(&self) -> Option<&K> {
+        self.heap.peek()
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// A removed node is its own parent.
+    fn node(&self, handle: BrandedHandle<'id, K>) -> Result<NodePtr<K>, HeapError> {
+        if unsafe { (*handle.node.as_ptr()).parent } == Some(handle.node) {
+            Err(HeapError::StaleHandle)
+        } else {
+            Ok(handle.node)
+        }
+    }
+
+    /// Moves the key out of the detached node `nn` and keeps the node until the scope ends.
+    unsafe fn retire(&mut self, nn: NodePtr<K>) -> K {
+        let node = nn.as_ptr();
+        (*node).slot = None;
+        (*node).parent = Some(nn);
+        (*node).next = self.retired;
+        self.retired = Some(nn);
+        std::ptr::read(&(*node).key)
+    }
+}
+
+impl<K, C> Drop for Scope<'_, '_, K, C> {
+    fn drop(&mut self) {
+        while let Some(nn) = self.retired {
+            unsafe {
+                self.retired = (*nn.as_ptr()).next;
+                // the key was moved out, so only the memory is released
+                std::alloc::dealloc(nn.as_ptr() as *mut u8, Layout::new::<Node<K>>());
+            }
+        }
+    }
+}
+
+impl<K, C: Compare<K>> BinomialHeap<K, C> {
+    /// Returns the elements in the order `delete_min` would return them.
+    pub fn into_sorted_vec(self) -> Vec<K> {
+        self.into_iter_sorted().collect()
+    }
+
+    /// Returns an iterator that yields the elements in heap order by repeated `delete_min`.
+    pub fn into_iter_sorted(self) -> IntoIterSorted<K, C> {
+        IntoIterSorted { heap: self }
+    }
+
+    /// Removes the elements in heap order.
+    ///
+    /// Elements not yielded are dropped together with the iterator.
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, K, C> {
+        DrainSorted { heap: self }
+    }
+
+    /// Returns an iterator that yields the keys in heap order without modifying the heap.
+    ///
+    /// A frontier of candidates starts with the roots; each yielded key adds its children, of
+    /// which there are at most log n. The first k keys take O(k log n log k) time, plus O(r) to
+    /// start with r roots.
+    pub fn iter_sorted(&self) -> IterSorted<'_, K, C> {
+        let mut roots = Vec::new();
+        if let Some(min) = self.min {
+            Candidate::for_list(min, &self.cmp, |c| roots.push(c));
+        }
+        IterSorted {
+            frontier: roots.into(),
+            len: self.size,
+        }
+    }
+
+    /// Returns the `k` first keys in heap order, or all of them if there are fewer.
+    pub fn k_smallest(&self, k: usize) -> Vec<&K> {
+        self.iter_sorted().take(k).collect()
+    }
+}
+
+/// Node of the frontier of [`IterSorted`], ordered so that `BinaryHeap` pops the first one.
+struct Candidate<'a, K, C> {
+    node: &'a Node<K>,
+    cmp: &'a C,
+}
+
+impl<'a, K, C> Candidate<'a, K, C> {
+    /// Calls `push` on every node of the circular list containing `first`.
+    fn for_list<F: FnMut(Self)>(first: NodePtr<K>, cmp: &'a C, mut push: F) {
+        let mut p = first;
+        loop {
+            let node = unsafe { &*p.as_ptr() };
+            push(Candidate { node, cmp });
+            p = node.next.unwrap();
+            if p == first {
+                break;
+            }
+        }
+    }
+}
+
+impl<K, C> Clone for Candidate<'_, K, C> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            cmp: self.cmp,
+        }
+    }
+}
+
+impl<K, C: Compare<K>> PartialEq for Candidate<'_, K, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K, C: Compare<K>> Eq for Candidate<'_, K, C> {}
+
+impl<K, C: Compare<K>> PartialOrd for Candidate<'_, K, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, C: Compare<K>> Ord for Candidate<'_, K, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp.compare(&other.node.key, &self.node.key)
+    }
+}
+
+/// Iterator returned by [`BinomialHeap::iter_sorted`].
+pub struct IterSorted<'a, K, C = MinOrder> {
+    frontier: BinaryHeap<Candidate<'a, K, C>>,
+    len: usize,
+}
+
+impl<'a, K, C: Compare<K>> Iterator for IterSorted<'a, K, C> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        let Candidate { node, cmp } = self.frontier.pop()?;
+        if let Some(child) = node.child {
+            Candidate::for_list(child, cmp, |c| self.frontier.push(c));
+        }
+        self.len -= 1;
+        Some(&node.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, C: Compare<K>> ExactSizeIterator for IterSorted<'_, K, C> {}
+
+impl<K, C> Clone for IterSorted<'_, K, C> {
+    fn clone(&self) -> Self {
+        Self {
+            frontier: self.frontier.clone(),
+            len: self.len,
+        }
+    }
+}
+
+/// Iterator returned by [`BinomialHeap::into_iter_sorted`].
+pub struct IntoIterSorted<K, C = MinOrder> {
+    heap: BinomialHeap<K, C>,
+}
+
+impl<K, C: Compare<K>> Iterator for IntoIterSorted<K, C> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.heap.delete_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+impl<K, C: Compare<K>> ExactSizeIterator for IntoIterSorted<K, C> {}
+
+/// Iterator returned by [`BinomialHeap::drain_sorted`].
+pub struct DrainSorted<'a, K, C = MinOrder> {
+    heap: &'a mut BinomialHeap<K, C>,
+}
+
+impl<K, C: Compare<K>> Iterator for DrainSorted<'_, K, C> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.heap.delete_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+impl<K, C: Compare<K>> ExactSizeIterator for DrainSorted<'_, K, C> {}
+
+impl<K, C> Drop for DrainSorted<'_, K, C> {
+    fn drop(&mut self) {
+        self.heap.clear();
+    }
+}
+
+impl<K, C: Compare<K> + Default> FromIterator<K> for BinomialHeap<K, C> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut heap = Self::default();
+        heap.extend(iter);
+        heap
+    }
+}
+
+impl<K, C: Compare<K>> Extend<K> for BinomialHeap<K, C> {
+    /// Builds a binomial forest of the new keys directly by linking equal-degree trees, in O(n),
+    /// and splices it into the root list.
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        let mut batch = Batch {
+            heap: self,
+            head: None,
+            size: 0,
+        };
+        for key in iter {
+            let node = Node::new_ptr(key);
+            unsafe {
+                (*node.as_ptr()).next = batch.head;
+            }
+            batch.head = Some(node);
+            batch.size += 1;
+        }
+    }
+}
+
+/// New nodes of `extend`, spliced into the heap on drop, so none is lost if the iterator panics.
+struct Batch<'a, K, C: Compare<K>> {
+    heap: &'a mut BinomialHeap<K, C>,
+    head: Option<NodePtr<K>>,
+    size: usize,
+}
+
+impl<K, C: Compare<K>> Drop for Batch<'_, K, C> {
+    fn drop(&mut self) {
+        if let Some(head) = self.head.take() {
+            let heap = &mut *self.heap;
+            heap.splice(BinomialHeap::merge_list(&heap.cmp, head, None, self.size));
+            heap.size += self.size;
+        }
+    }
+}
+
+impl<K: Ord> From<Vec<K>> for BinomialHeap<K> {
+    fn from(vec: Vec<K>) -> Self {
+        vec.into_iter().collect()
+    }
+}
+
+impl<K: Ord, const N: usize> From<[K; N]> for BinomialHeap<K> {
+    fn from(arr: [K; N]) -> Self {
+        arr.into_iter().collect()
     }
+}
 
-    /// nodeを指すポインタが残っていてはならない
-    fn into_key(node: NonNull<Self>) -> K {
-        unsafe {
-            let n = std::ptr::read(node.as_ptr() as *const Self);
-            std::alloc::dealloc(node.as_ptr() as *mut _, Layout::new::<Self>());
-            n.key
+impl<K, C> BinomialHeap<K, C> {
+    /// Consumes the heap and passes every key to `f` in arbitrary order, together with the memory
+    /// of its node.
+    pub(super) fn into_recycled<F: FnMut(K, Recycled)>(self, mut f: F) -> C {
+        let mut this = ManuallyDrop::new(self);
+        let cmp = unsafe { std::ptr::read(&this.cmp) };
+        drop(std::mem::replace(&mut this.id, Identity::new()));
+        let mut head = IntoIter::new(this.min.take(), 0).head.take();
+        while let Some(node) = Node::pop_front(&mut head) {
+            let Node { key, slot, .. } = unsafe { std::ptr::read(node.as_ptr()) };
+            drop(slot);
+            f(key, unsafe { Recycled::new(node) });
         }
+        cmp
     }
 }
 
-impl<K> BinomialHeap<K> {
-    pub const fn new() -> Self {
-        Self {
-            min: None,
-            size: 0,
-            marker: PhantomData,
+/// Builds the binomial nodes in the memory of the pairing ones, which are smaller and so are
+/// usually moved by the allocator, and links them into a binomial forest in O(n).
+impl<K, C: Compare<K>> From<PairingHeap<K, C>> for BinomialHeap<K, C> {
+    fn from(heap: PairingHeap<K, C>) -> Self {
+        let mut head = None;
+        let mut size = 0;
+        let cmp = heap.into_recycled(|key, memory| {
+            let node = memory.reuse(Node::new(key));
+            unsafe { (*node.as_ptr()).next = head };
+            head = Some(node);
+            size += 1;
+        });
+        let mut binomial = Self::with_compare(cmp);
+        if let Some(head) = head {
+            binomial.min = Some(Self::merge_list(&binomial.cmp, head, None, size));
+            binomial.size = size;
         }
+        binomial
     }
+}
 
-    pub const fn len(&self) -> usize {
-        self.size
+/// Collects the elements in O(n) into a heap with the same max-first order.
+impl<K: Ord> From<BinaryHeap<K>> for BinomialHeap<K, MaxOrder> {
+    fn from(heap: BinaryHeap<K>) -> Self {
+        heap.into_vec().into_iter().collect()
     }
 }
 
-impl<K: Ord> BinomialHeap<K> {
-    fn merge(mut root: NonNull<Node<K>>, mut other: NonNull<Node<K>>) -> NonNull<Node<K>> {
-        unsafe {
-            if root.as_ref().key > other.as_ref().key {
-                if let Some(och) = other.as_ref().child {
-                    (*other.as_ptr()).child = Some(Self::insert_node(och, root));
-                } else {
-                    root.as_mut().prev = Some(root);
-                    root.as_mut().next = Some(root);
-                    other.as_mut().child = Some(root);
-                }
-                other.as_mut().degree += 1;
-                other
-            } else {
-                if let Some(rch) = root.as_ref().child {
-                    (*root.as_ptr()).child = Some(Self::insert_node(rch, other));
-                } else {
-                    other.as_mut().prev = Some(other);
-                    other.as_mut().next = Some(other);
-                    root.as_mut().child = Some(other);
-                }
-                root.as_mut().degree += 1;
-                root
-            }
-        }
+impl<K: Ord> From<BinaryHeap<Reverse<K>>> for BinomialHeap<K> {
+    fn from(heap: BinaryHeap<Reverse<K>>) -> Self {
+        heap.into_vec()
+            .into_iter()
+            .map(|Reverse(key)| key)
+            .collect()
     }
+}
 
-    fn insert_node(min: NonNull<Node<K>>, node: NonNull<Node<K>>) -> NonNull<Node<K>> {
-        unsafe {
-            Self::insert_node_next(min, node);
-            if (*min.as_ptr()).key > (*node.as_ptr()).key {
-                node
-            } else {
-                min
-            }
-        }
+/// Heapifies the elements in O(n).
+impl<K: Ord, C> From<BinomialHeap<K, C>> for BinaryHeap<K> {
+    fn from(heap: BinomialHeap<K, C>) -> Self {
+        BinaryHeap::from(heap.into_iter().collect::<Vec<_>>())
     }
+}
 
-    fn insert_node_prev(ptr: NonNull<Node<K>>, node: NonNull<Node<K>>) {
-        unsafe {
-            let prev = ptr.as_ref().prev.unwrap();
-            (*prev.as_ptr()).next = Some(node);
-            (*ptr.as_ptr()).prev = Some(node);
-            (*node.as_ptr()).next = Some(ptr);
-            (*node.as_ptr()).prev = Some(prev);
-        }
+/// Heapifies the elements in O(n), keeping the minimum on top.
+impl<K: Ord, C> From<BinomialHeap<K, C>> for BinaryHeap<Reverse<K>> {
+    fn from(heap: BinomialHeap<K, C>) -> Self {
+        BinaryHeap::from(heap.into_iter().map(Reverse).collect::<Vec<_>>())
     }
+}
 
-    fn insert_node_next(ptr: NonNull<Node<K>>, node: NonNull<Node<K>>) {
-        unsafe {
-            let next = ptr.as_ref().next.unwrap();
-            (*next.as_ptr()).prev = Some(node);
-            (*ptr.as_ptr()).next = Some(node);
-            (*node.as_ptr()).prev = Some(ptr);
-            (*node.as_ptr()).next = Some(next);
-        }
+impl<K, C: Compare<K>> PeekMut<'_, K, C> {
+    /// Removes the peeked element from the heap.
+    pub fn pop(mut this: Self) -> K {
+        this.modified = false;
+        this.heap.delete_min().unwrap()
     }
+}
 
-    pub fn insert(&mut self, key: K) {
-        if let Some(nn) = self.min {
-            unsafe {
-                if nn.as_ref().key > key {
-                    let new_node = Node::new_ptr(key);
-                    Self::insert_node_prev(nn, new_node);
-                    self.min = Some(new_node);
-                } else {
-                    let new_node = Node::new_ptr(key);
-                    Self::insert_node_prev(nn, new_node);
-                }
-            }
-        } else {
-            self.min = Some(Node::new_cyclic(key));
-        }
-        self.size += 1;
+impl<K, C: Compare<K>> Deref for PeekMut<'_, K, C> {
+    type Target = K;
+
+    fn deref(&self) -> &K {
+        self.heap.peek().unwrap()
     }
+}
 
-    /// Returns node whose key is minimum
-    fn merge_list(
-        head: NonNull<Node<K>>,
-        head2: Option<NonNull<Node<K>>>,
-        size: usize,
-    ) -> NonNull<Node<K>> {
-        let mut v = vec![None; size.ilog2() as usize + 1];
-        let mut p = Some(head);
-        unsafe {
-            while let Some(mut ne) = p {
-                p = (*ne.as_ptr()).next;
-                while let Some(other) = v[(*ne.as_ptr()).degree].take() {
-                    ne = Self::merge(ne, other);
-                }
-                v[(*ne.as_ptr()).degree] = Some(ne);
-                if p == Some(head) {
-                    break;
-                }
-            }
-            p = head2;
-            while let Some(mut ne) = p {
-                p = (*ne.as_ptr()).next;
-                while let Some(other) = v[(*ne.as_ptr()).degree].take() {
-                    ne = Self::merge(ne, other);
-                }
-                v[(*ne.as_ptr()).degree] = Some(ne);
-                if p == head2 {
-                    break;
-                }
-            }
-            let mut itr = v.into_iter().filter_map(|e| e);
-            let head = itr.next().unwrap();
-            let mut min = head;
-            let mut p = head;
-            for item in itr {
-                (*p.as_ptr()).next = Some(item);
-                (*item.as_ptr()).prev = Some(p);
-                p = item;
-                if (*min.as_ptr()).key > (*item.as_ptr()).key {
-                    min = item;
-                }
-            }
-            (*p.as_ptr()).next = Some(head);
-            (*head.as_ptr()).prev = Some(p);
-            min
-        }
+impl<K, C: Compare<K>> DerefMut for PeekMut<'_, K, C> {
+    fn deref_mut(&mut self) -> &mut K {
+        self.modified = true;
+        unsafe { &mut (*self.heap.min.unwrap().as_ptr()).key }
     }
+}
 
-    pub fn delete_min(&mut self) -> Option<K> {
-        if let Some(min) = self.min {
-            unsafe {
-                let prev = (*min.as_ptr()).prev.unwrap();
-                if prev == min {
-                    self.min = (*min.as_ptr()).child;
-                } else {
-                    (*prev.as_ptr()).next = None;
-                    self.min = Some(Self::merge_list(
-                        (*min.as_ptr()).next.unwrap(),
-                        (*min.as_ptr()).child,
-                        self.size,
-                    ));
-                }
-                self.size -= 1;
-                Some(Node::into_key(min))
-            }
-        } else {
-            None
+impl<K, C: Compare<K>> Drop for PeekMut<'_, K, C> {
+    fn drop(&mut self) {
+        if self.modified {
+            self.heap.sift_min();
         }
     }
 }
@@ -262,33 +1463,217 @@ impl<K: Display> Node<K> {
     }
 }
 
-impl<K: Display> Debug for BinomialHeap<K> {
+impl<K: Display, C> Debug for BinomialHeap<K, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Node::fmt_tree(self.min, f, 0)
     }
 }
 
 impl<K> Node<K> {
-    fn drop_node(mut node: Option<NonNull<Node<K>>>) {
-        let end = node;
-        let layout = Layout::new::<Self>();
-        while let Some(p) = node {
+    /// Detaches the front of the linear chain `head`, splicing its children in its place.
+    fn pop_front(head: &mut Option<NonNull<Self>>) -> Option<NonNull<Self>> {
+        let node = (*head)?;
+        unsafe {
+            if let Some(ch) = (*node.as_ptr()).child.take() {
+                let last = (*ch.as_ptr()).prev.unwrap();
+                (*last.as_ptr()).next = (*node.as_ptr()).next;
+                *head = Some(ch);
+            } else {
+                *head = (*node.as_ptr()).next;
+            }
+        }
+        Some(node)
+    }
+}
+
+impl<K, C> BinomialHeap<K, C> {
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            stack: self.min.map(|min| (min, min)).into_iter().collect(),
+            len: self.size,
+            marker: PhantomData,
+        }
+    }
+
+    /// Removes all elements in arbitrary order.
+    ///
+    /// The heap is emptied immediately, even if the iterator is not consumed.
+    pub fn drain(&mut self) -> Drain<'_, K> {
+        Drain {
+            iter: IntoIter::new(self.min.take(), std::mem::take(&mut self.size)),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.drain();
+    }
+
+    /// Rewrites every key in place with `f`, without relinking or allocating, in O(n).
+    ///
+    /// `f` must be monotone in the heap order: if `a` comes before `b`, `f(a)` must not come
+    /// after `f(b)`. The heap order is then preserved, and handles keep pointing to their
+    /// elements. Otherwise, or if `f` panics halfway, the order of later operations is
+    /// unspecified, but the heap stays safe to use and drop.
+    ///
+    /// Shifting every key by the same delta is done with this method too. A lazy offset at the
+    /// roots would have to be added to every key that `peek`, the iterators and the handles lend
+    /// out by reference, so none is kept.
+    pub fn map_keys_monotone<F: FnMut(&mut K)>(&mut self, mut f: F) {
+        let Some(start) = self.min else {
+            return;
+        };
+        let mut p = start;
+        unsafe {
+            'walk: loop {
+                f(&mut (*p.as_ptr()).key);
+                if let Some(child) = (*p.as_ptr()).child {
+                    p = child;
+                    continue;
+                }
+                // step to the next sibling, climbing to the parent when the list wraps around
+                loop {
+                    let parent = (*p.as_ptr()).parent;
+                    let first = parent.map_or(start, |parent| (*parent.as_ptr()).child.unwrap());
+                    let next = (*p.as_ptr()).next.unwrap();
+                    if next != first {
+                        p = next;
+                        continue 'walk;
+                    }
+                    match parent {
+                        Some(parent) => p = parent,
+                        None => break 'walk,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Iterator over the elements of a [`BinomialHeap`] in arbitrary order.
+pub struct Iter<'a, K> {
+    /// Nodes to visit, paired with the first node of their sibling list.
+    stack: Vec<(NodePtr<K>, NodePtr<K>)>,
+    len: usize,
+    marker: PhantomData<&'a Node<K>>,
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        let (node, first) = self.stack.pop()?;
+        unsafe {
+            let next = (*node.as_ptr()).next.unwrap();
+            if next != first {
+                self.stack.push((next, first));
+            }
+            if let Some(ch) = (*node.as_ptr()).child {
+                self.stack.push((ch, ch));
+            }
+            self.len -= 1;
+            Some(&(*node.as_ptr()).key)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K> ExactSizeIterator for Iter<'_, K> {}
+
+impl<K> Clone for Iter<'_, K> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Owning iterator over the elements of a [`BinomialHeap`] in arbitrary order.
+pub struct IntoIter<K> {
+    /// Root list opened into a chain terminated by `None`.
+    head: Option<NonNull<Node<K>>>,
+    len: usize,
+    marker: PhantomData<Box<Node<K>>>,
+}
+
+impl<K> IntoIter<K> {
+    fn new(min: Option<NonNull<Node<K>>>, len: usize) -> Self {
+        if let Some(min) = min {
             unsafe {
-                let Node {
-                    next,
-                    prev: _,
-                    child,
-                    degree: _,
-                    key: _,
-                } = std::ptr::read(p.as_ptr());
-                std::alloc::dealloc(p.as_ptr() as *mut _, layout);
-                node = next;
-                Self::drop_node(child);
-            }
-            if node == end {
-                break;
+                let prev = (*min.as_ptr()).prev.unwrap();
+                (*prev.as_ptr()).next = None;
             }
         }
+        Self {
+            head: min,
+            len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K> Iterator for IntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        let node = Node::pop_front(&mut self.head)?;
+        self.len -= 1;
+        Some(Node::into_key(node))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K> ExactSizeIterator for IntoIter<K> {}
+
+impl<K> Drop for IntoIter<K> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+/// Draining iterator returned by [`BinomialHeap::drain`].
+pub struct Drain<'a, K> {
+    iter: IntoIter<K>,
+    marker: PhantomData<&'a mut Node<K>>,
+}
+
+impl<K> Iterator for Drain<'_, K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K> ExactSizeIterator for Drain<'_, K> {}
+
+impl<K, C> IntoIterator for BinomialHeap<K, C> {
+    type Item = K;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(mut self) -> IntoIter<K> {
+        IntoIter::new(self.min.take(), std::mem::take(&mut self.size))
+    }
+}
+
+impl<'a, K, C> IntoIterator for &'a BinomialHeap<K, C> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Iter<'a, K> {
+        self.iter()
     }
 }
 
@@ -303,6 +1688,8 @@ impl<K: Clone> Node<K> {
                     next: None,
                     prev: None,
                     child: (*node.as_ptr()).child.map(Self::clone_node),
+                    parent: None,
+                    slot: None,
                     degree: (*node.as_ptr()).degree,
                     key: (*node.as_ptr()).key.clone(),
                 },
@@ -321,6 +1708,8 @@ impl<K: Clone> Node<K> {
                         next: None,
                         prev: Some(prev),
                         child: (*p.as_ptr()).child.map(Self::clone_node),
+                        parent: None,
+                        slot: None,
                         degree: (*p.as_ptr()).degree,
                         key: (*p.as_ptr()).key.clone(),
                     },
@@ -332,22 +1721,62 @@ impl<K: Clone> Node<K> {
             }
             (*prev.as_ptr()).next = Some(new_node);
             (*new_node.as_ptr()).prev = Some(prev);
+            let mut p = new_node;
+            loop {
+                if let Some(first) = (*p.as_ptr()).child {
+                    let mut ch = first;
+                    loop {
+                        (*ch.as_ptr()).parent = Some(p);
+                        ch = (*ch.as_ptr()).next.unwrap();
+                        if ch == first {
+                            break;
+                        }
+                    }
+                }
+                p = (*p.as_ptr()).next.unwrap();
+                if p == new_node {
+                    break;
+                }
+            }
             new_node
         }
     }
 }
 
-impl<K> Drop for BinomialHeap<K> {
+impl<K, C> Drop for BinomialHeap<K, C> {
     fn drop(&mut self) {
-        Node::drop_node(self.min);
+        self.drain();
+    }
+}
+
+/// Compares the keys as multisets, whatever the shape of the trees.
+///
+/// Both heaps are walked in heap order, in O(n log n). Keys that the comparator of `self`
+/// considers equivalent are matched with `==` in any order, which costs O(r²) comparisons for
+/// each run of r equivalent keys, so O(n²) if all of them are equivalent.
+impl<K: PartialEq, C: Compare<K>> PartialEq for BinomialHeap<K, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && super::sorted_multiset_eq(self.iter_sorted(), other.iter_sorted(), &self.cmp)
+    }
+}
+
+impl<K: Eq, C: Compare<K>> Eq for BinomialHeap<K, C> {}
+
+/// Hashes the keys in an order-independent way, consistent with `PartialEq`.
+impl<K: Hash, C> Hash for BinomialHeap<K, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        super::hash_unordered(self.iter(), state);
     }
 }
 
-impl<K: Clone> Clone for BinomialHeap<K> {
+impl<K: Clone, C: Clone> Clone for BinomialHeap<K, C> {
     fn clone(&self) -> Self {
         Self {
             min: self.min.map(Node::clone_node),
             size: self.size,
+            cmp: self.cmp.clone(),
+            id: Identity::new(),
             marker: PhantomData,
         }
     }
@@ -464,4 +1893,138 @@ mod test {
         println!("{:?}", heap);
         println!("{:?}", heap.clone());
     }
+
+    #[test]
+    fn meld_test() {
+        let mut a = BinomialHeap::new();
+        let mut b = BinomialHeap::new();
+        for i in 0..40 {
+            if i % 3 == 0 {
+                a.insert(i);
+            } else {
+                b.insert(i);
+            }
+        }
+        a.delete_min();
+        b.delete_min();
+        a.meld_eager(b);
+        assert_eq!(a.len(), 38);
+        let mut c = BinomialHeap::meld_all((0..5).map(|i| {
+            let mut h = BinomialHeap::new();
+            h.insert(-i);
+            h
+        }));
+        a.append(&mut c);
+        assert!(c.is_empty());
+        let v: Vec<i32> = (0..a.len()).map(|_| a.delete_min().unwrap()).collect();
+        let expected: Vec<i32> = (-4..=0).chain(2..40).collect();
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn convert_test() {
+        let mut heap: BinomialHeap<i32> = (0..40).rev().collect();
+        heap.delete_min();
+        let pairing = PairingHeap::from(heap);
+        assert_eq!(pairing.len(), 39);
+        let heap = BinomialHeap::from(pairing);
+        let max: BinaryHeap<i32> = heap.clone().into();
+        assert_eq!(max.peek(), Some(&39));
+        let min: BinaryHeap<Reverse<i32>> = heap.into();
+        assert_eq!(min.peek(), Some(&Reverse(1)));
+        assert_eq!(
+            BinomialHeap::from(max).into_sorted_vec(),
+            (1..40).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            BinomialHeap::<i32>::from(min).into_sorted_vec(),
+            (1..40).collect::<Vec<_>>()
+        );
+
+        let mut pairing = PairingHeap::new();
+        let mut handles: Vec<_> = (0..50)
+            .map(|i| pairing.insert_with_handle((i * 7 % 50).to_string()))
+            .collect();
+        pairing.remove(handles.swap_remove(3)).unwrap();
+        pairing.delete_min();
+        let mut expected: Vec<_> = pairing.iter().cloned().collect();
+        expected.sort();
+        let heap = BinomialHeap::from(pairing);
+        assert_eq!(heap.len(), 48);
+        assert_eq!(heap.into_sorted_vec(), expected);
+    }
+
+    #[test]
+    fn decrease_key_test() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut heap = BinomialHeap::new();
+        // distinct values, so popped elements can be told apart by value
+        let mut live: Vec<_> = (0..300)
+            .map(|i| i * 37 % 300 * 10)
+            .map(|v| (heap.insert_with_handle(v), v))
+            .collect();
+        let mut rest: Vec<i32> = (0..100).map(|i| i * 10 + 5).collect();
+        heap.extend(rest.iter().copied());
+        for _ in 0..20 {
+            let min = heap.delete_min().unwrap();
+            live.retain(|&(_, v)| v != min);
+            rest.retain(|&v| v != min);
+        }
+        // every decrease moves keys between nodes, so handles must follow them
+        for _ in 0..3 {
+            for (h, v) in live.iter_mut().take(100) {
+                *v -= rng.gen_range(0..2000);
+                heap.decrease_key(h, *v).unwrap();
+            }
+        }
+        let mut vals: Vec<i32> = live.iter().map(|&(_, v)| v).chain(rest).collect();
+        vals.sort();
+        assert_eq!(heap.peek(), vals.first());
+        assert_eq!(heap.clone().into_sorted_vec(), vals);
+        assert_eq!(heap.into_sorted_vec(), vals);
+    }
+
+    #[test]
+    fn scope_relink_test() {
+        let mut heap = BinomialHeap::new();
+        let checked: Vec<_> = (0..64).map(|i| heap.insert_with_handle(1000 + i)).collect();
+        let mut expected: Vec<i32> = (0..64).map(|i| 1000 + i).collect();
+        heap.scope(|mut s| {
+            let handles: Vec<_> = (0..200)
+                .map(|i| s.insert_with_handle(i * 37 % 200))
+                .collect();
+            expected.extend((0..200).map(|i| i * 37 % 200));
+            // consolidate the root list so that nodes have parents to trade places with
+            s.insert(-1);
+            assert_eq!(s.delete_min(), Some(-1));
+            for (i, &h) in handles.iter().enumerate().step_by(7) {
+                let old = i as i32 * 37 % 200;
+                let new = -(i as i32);
+                s.decrease_key(h, new).unwrap();
+                let pos = expected.iter().position(|&v| v == old).unwrap();
+                expected[pos] = new;
+            }
+            for (i, &h) in handles.iter().enumerate().skip(3).step_by(11) {
+                let val = s.remove(h).unwrap();
+                assert!(val == i as i32 * 37 % 200 || val == -(i as i32));
+                let pos = expected.iter().position(|&v| v == val).unwrap();
+                expected.swap_remove(pos);
+                assert_eq!(s.remove(h), Err(HeapError::StaleHandle));
+            }
+            s.update(handles[1], |v| v + 500).unwrap();
+            let pos = expected.iter().position(|&v| v == 37).unwrap();
+            expected[pos] = 537;
+        });
+        // handles checked at run time still reach their elements after the nodes moved
+        for (i, h) in checked.iter().enumerate().step_by(5) {
+            heap.decrease_key(h, -1000 - i as i32).unwrap();
+            let pos = expected.iter().position(|&v| v == 1000 + i as i32).unwrap();
+            expected[pos] = -1000 - i as i32;
+        }
+        expected.sort();
+        assert_eq!(heap.len(), expected.len());
+        assert_eq!(heap.into_sorted_vec(), expected);
+    }
 }
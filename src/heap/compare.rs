@@ -0,0 +1,105 @@
+use std::cmp::Ordering;
+
+/// Ordering strategy of a heap. The element that compares `Less` is served first.
+pub trait Compare<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+
+    fn lt(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) == Ordering::Less
+    }
+
+    fn gt(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) == Ordering::Greater
+    }
+}
+
+/// Natural order of `Ord`; the heap serves the smallest element first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MinOrder;
+
+impl<T: Ord + ?Sized> Compare<T> for MinOrder {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Reversed order of `Ord`; the heap serves the largest element first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MaxOrder;
+
+impl<T: Ord + ?Sized> Compare<T> for MaxOrder {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// Order given by a closure `Fn(&T, &T) -> Ordering`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FnCompare<F>(pub F);
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for FnCompare<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a, b)
+    }
+}
+
+/// Natural order of the key extracted by a closure `Fn(&T) -> K`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeyCompare<F>(pub F);
+
+impl<T: ?Sized, K: Ord, F: Fn(&T) -> K> Compare<T> for KeyCompare<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+/// Orders `(key, value)` pairs by the key alone with `C`; the value takes no part in comparisons.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ByKey<C = MinOrder>(pub C);
+
+impl<K, V, C: Compare<K>> Compare<(K, V)> for ByKey<C> {
+    fn compare(&self, a: &(K, V), b: &(K, V)) -> Ordering {
+        self.0.compare(&a.0, &b.0)
+    }
+}
+
+/// Total order on floats: numbers compare as usual (`-0.0 == 0.0`) and every NaN is greater than
+/// any number, so NaNs are served last.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TotalOrder;
+
+macro_rules! impl_total_order {
+    ($($t:ty),*) => {$(
+        impl Compare<$t> for TotalOrder {
+            fn compare(&self, a: &$t, b: &$t) -> Ordering {
+                a.partial_cmp(b)
+                    .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+            }
+        }
+    )*};
+}
+
+impl_total_order!(f32, f64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn total_order() {
+        let mut v = [3.0, f64::NAN, -1.0, f64::INFINITY, -0.0, 0.0];
+        v.sort_by(|a, b| TotalOrder.compare(a, b));
+        assert_eq!(&v[..3], &[-1.0, -0.0, 0.0]);
+        assert_eq!(v[4], f64::INFINITY);
+        assert!(v[5].is_nan());
+        assert_eq!(TotalOrder.compare(&f32::NAN, &f32::NAN), Ordering::Equal);
+    }
+
+    #[test]
+    fn adapters() {
+        assert!(MaxOrder.lt(&2, &1));
+        assert!(FnCompare(|a: &i32, b: &i32| b.cmp(a)).gt(&1, &2));
+        assert!(KeyCompare(|s: &&str| s.len()).lt(&"zz", &"aaa"));
+        assert!(ByKey(MaxOrder).lt(&(2, "a"), &(1, "b")));
+    }
+}
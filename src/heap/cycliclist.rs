@@ -1,20 +1,30 @@
 use std::cell::RefCell;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
-#[allow(dead_code)]
+
+use super::handle::{Identity, Owner};
+
 struct Node<T> {
     key: T,
     prev: RefCell<Option<Rc<Node<T>>>>,
     next: RefCell<Weak<Node<T>>>,
+    /// Identity of the list holding the node, `None` once it is unlinked.
+    owner: RefCell<Option<Rc<Owner>>>,
 }
 
-#[allow(dead_code)]
+/// Doubly linked list whose ends are joined: the element after the last one is the first one.
+///
+/// Each node is owned by the `prev` link of its successor, and the last one by the list itself,
+/// so an [`Indexer`] keeps its node alive but never the rest of the list. Every method taking an
+/// indexer checks that its element is in this list.
 pub struct CyclicList<T> {
-    head: Rc<Node<T>>,
-    tail: Rc<Node<T>>,
+    head: Option<Rc<Node<T>>>,
+    tail: Option<Rc<Node<T>>>,
     size: usize,
+    id: Identity,
 }
 
-#[allow(dead_code)]
+/// Cursor pointing to an element of a [`CyclicList`].
 pub struct Indexer<T>(Rc<Node<T>>);
 
 impl<T> Clone for Indexer<T> {
@@ -25,11 +35,12 @@ impl<T> Clone for Indexer<T> {
 
 #[allow(dead_code)]
 impl<T> Node<T> {
-    fn new(key: T, prev: Option<Rc<Node<T>>>, next: Weak<Node<T>>) -> Self {
+    fn new(key: T, prev: Option<Rc<Node<T>>>, next: Weak<Node<T>>, owner: Rc<Owner>) -> Self {
         Self {
             key,
             prev: RefCell::new(prev),
             next: RefCell::new(next),
+            owner: RefCell::new(Some(owner)),
         }
     }
 
@@ -54,9 +65,15 @@ impl<T> Indexer<T> {
     pub fn key(&self) -> &T {
         &self.0.key
     }
+
+    /// Returns whether both indexers point to the same element.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 impl<T> Indexer<T> {
+    /// Takes the element out, failing while the list or another indexer still refers to it.
     pub fn into_key(this: Self) -> Result<T, Self> {
         Rc::try_unwrap(this.0)
             .map(|n| n.key)
@@ -64,43 +81,122 @@ impl<T> Indexer<T> {
     }
 }
 
-#[allow(dead_code)]
 impl<T> CyclicList<T> {
+    /// Creates a list holding only `key`.
     pub fn new(key: T) -> Self {
-        let new_node = Rc::new(Node::new(key, None, Weak::new()));
+        let mut id = Identity::new();
+        let new_node = Rc::new(Node::new(key, None, Weak::new(), id.get()));
         Self {
-            head: Rc::clone(&new_node),
-            tail: new_node,
+            head: Some(Rc::clone(&new_node)),
+            tail: Some(new_node),
             size: 1,
+            id,
         }
     }
 
+    /// Returns an indexer to the first element.
+    ///
+    /// # Panics
+    /// Panics if the list is empty.
     pub fn take_one(&self) -> Indexer<T> {
-        Indexer(Rc::clone(&self.head))
+        Indexer(Rc::clone(self.head.as_ref().expect("the list is empty")))
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.as_ref().map(|n| &n.key)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.tail.as_ref().map(|n| &n.key)
     }
 
+    /// Returns the element after `node`, which is the first one after the last.
+    ///
+    /// # Panics
+    /// Panics if `node` does not belong to this list.
     pub fn next(&self, node: &Indexer<T>) -> Indexer<T> {
+        self.check(node);
         if let Some(n) = node.0.next.borrow().upgrade() {
             Indexer(n)
         } else {
-            Indexer(Rc::clone(&self.head))
+            self.take_one()
         }
     }
 
+    /// Returns the element before `node`, which is the last one before the first.
+    ///
+    /// # Panics
+    /// Panics if `node` does not belong to this list.
     pub fn prev(&self, node: &Indexer<T>) -> Indexer<T> {
+        self.check(node);
         if let Some(n) = node.0.prev.borrow().as_ref() {
             Indexer(Rc::clone(n))
         } else {
-            Indexer(Rc::clone(&self.tail))
+            Indexer(Rc::clone(self.tail.as_ref().expect("the list is empty")))
         }
     }
 
-    // Returns inserted node
+    pub fn push_back(&mut self, key: T) -> Indexer<T> {
+        match &self.tail {
+            Some(tail) => {
+                let tail = Indexer(Rc::clone(tail));
+                self.insert_next(&tail, key)
+            }
+            None => {
+                *self = Self::new(key);
+                self.take_one()
+            }
+        }
+    }
+
+    pub fn push_front(&mut self, key: T) -> Indexer<T> {
+        match &self.head {
+            Some(head) => {
+                let head = Indexer(Rc::clone(head));
+                self.insert_prev(&head, key)
+            }
+            None => {
+                *self = Self::new(key);
+                self.take_one()
+            }
+        }
+    }
+
+    /// Returns whether the element of `pos` is in this list.
+    pub fn owns(&self, pos: &Indexer<T>) -> bool {
+        pos.0
+            .owner
+            .borrow()
+            .as_ref()
+            .is_some_and(|owner| self.id.owns(owner))
+    }
+
+    /// Panics if the element of `pos` is not in this list, since following its links could
+    /// otherwise reach nodes of another list.
+    fn check(&self, pos: &Indexer<T>) {
+        assert!(self.owns(pos), "the indexer does not belong to this list");
+    }
+
+    fn is_tail(&self, pos: &Indexer<T>) -> bool {
+        self.tail.as_ref().is_some_and(|t| Rc::ptr_eq(&pos.0, t))
+    }
+
+    fn is_head(&self, pos: &Indexer<T>) -> bool {
+        self.head.as_ref().is_some_and(|h| Rc::ptr_eq(&pos.0, h))
+    }
+
+    /// Inserts `key` right after `pos` and returns an indexer to it.
+    ///
+    /// # Panics
+    /// Panics if `pos` does not belong to this list.
     pub fn insert_next(&mut self, pos: &Indexer<T>, key: T) -> Indexer<T> {
-        if Rc::ptr_eq(&pos.0, &self.tail) {
-            let new_tail = Rc::new(Node::new(key, Some(Rc::clone(&self.tail)), Weak::new()));
-            self.tail.link_next(Rc::downgrade(&new_tail));
-            self.tail = Rc::clone(&new_tail);
+        self.check(pos);
+        let owner = self.id.get();
+        if self.is_tail(pos) {
+            let tail = self.tail.take().unwrap();
+            let new_tail = Rc::new(Node::new(key, Some(Rc::clone(&tail)), Weak::new(), owner));
+            tail.link_next(Rc::downgrade(&new_tail));
+            self.tail = Some(Rc::clone(&new_tail));
             self.size += 1;
             Indexer(new_tail)
         } else {
@@ -109,6 +205,7 @@ impl<T> CyclicList<T> {
                 key,
                 Some(Rc::clone(&pos.0)),
                 Rc::downgrade(&old_next),
+                owner,
             ));
             pos.0.link_next(Rc::downgrade(&new_node));
             old_next.link_prev(Rc::clone(&new_node));
@@ -117,11 +214,16 @@ impl<T> CyclicList<T> {
         }
     }
 
+    /// # Panics
+    /// Panics if `pos` does not belong to this list.
     pub fn insert_prev(&mut self, pos: &Indexer<T>, key: T) -> Indexer<T> {
-        if Rc::ptr_eq(&pos.0, &self.head) {
-            let new_head = Rc::new(Node::new(key, None, Rc::downgrade(&self.head)));
-            self.head.link_prev(Rc::clone(&new_head));
-            self.head = Rc::clone(&new_head);
+        self.check(pos);
+        let owner = self.id.get();
+        if self.is_head(pos) {
+            let head = self.head.take().unwrap();
+            let new_head = Rc::new(Node::new(key, None, Rc::downgrade(&head), owner));
+            head.link_prev(Rc::clone(&new_head));
+            self.head = Some(Rc::clone(&new_head));
             self.size += 1;
             Indexer(new_head)
         } else {
@@ -130,6 +232,7 @@ impl<T> CyclicList<T> {
                 key,
                 Some(Rc::clone(&old_prev)),
                 Rc::downgrade(&pos.0),
+                owner,
             ));
             old_prev.link_next(Rc::downgrade(&new_node));
             pos.0.link_prev(Rc::clone(&new_node));
@@ -138,42 +241,225 @@ impl<T> CyclicList<T> {
         }
     }
 
-    // returns next node
-    pub fn delete(&mut self, pos: &Indexer<T>) -> Indexer<T> {
+    /// Unlinks the element of `pos` and returns the element after it, or `None` if the list
+    /// became empty.
+    ///
+    /// # Panics
+    /// Panics if `pos` does not belong to this list.
+    pub fn delete(&mut self, pos: &Indexer<T>) -> Option<Indexer<T>> {
+        self.check(pos);
+        pos.0.owner.replace(None);
         if self.size == 1 {
-            pos.clone()
-        } else if Rc::ptr_eq(&self.head, &pos.0) {
-            let new_head = self.head.next.replace(Weak::new()).upgrade().unwrap();
+            self.head = None;
+            self.tail = None;
+            self.size = 0;
+            None
+        } else if self.is_head(pos) {
+            let head = self.head.take().unwrap();
+            let new_head = head.next.replace(Weak::new()).upgrade().unwrap();
             *new_head.prev.borrow_mut() = None;
-            self.head = Rc::clone(&new_head);
+            self.head = Some(Rc::clone(&new_head));
             self.size -= 1;
-            Indexer(new_head)
-        } else if Rc::ptr_eq(&self.tail, &pos.0) {
-            let new_tail = self.tail.prev.replace(None).unwrap();
+            Some(Indexer(new_head))
+        } else if self.is_tail(pos) {
+            let tail = self.tail.take().unwrap();
+            let new_tail = tail.prev.replace(None).unwrap();
             *new_tail.next.borrow_mut() = Weak::new();
-            self.tail = new_tail;
+            self.tail = Some(new_tail);
             self.size -= 1;
-            Indexer(Rc::clone(&self.head))
+            Some(self.take_one())
         } else {
             let next = pos.0.next.replace(Weak::new()).upgrade().unwrap();
             let prev = pos.0.prev.replace(None).unwrap();
             *prev.next.borrow_mut() = Rc::downgrade(&next);
             *next.prev.borrow_mut() = Some(prev);
             self.size -= 1;
-            Indexer(next)
+            Some(Indexer(next))
+        }
+    }
+
+    /// Unlinks the element of `pos` and returns it.
+    ///
+    /// Returns the indexer back if another indexer to the element is still alive; the element
+    /// is unlinked anyway and can be taken later with [`Indexer::into_key`].
+    ///
+    /// # Panics
+    /// Panics if `pos` does not belong to this list.
+    pub fn remove(&mut self, pos: Indexer<T>) -> Result<T, Indexer<T>> {
+        self.delete(&pos);
+        Indexer::into_key(pos)
+    }
+
+    /// Moves all elements of `other` to the end of `self` in O(1), leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        match &self.tail {
+            Some(tail) => {
+                let tail = Indexer(Rc::clone(tail));
+                self.splice_next(&tail, other);
+            }
+            None => std::mem::swap(self, other),
         }
     }
 
-    // pub fn combine(&mut self, other: CyclicList<T>) {
-    //     let CyclicList{ head, tail , size: _} = other;
-    //     *self.tail.next.borrow_mut() = Rc::downgrade(&head);
-    //     *head.prev.borrow_mut() = Some(Rc::clone(&self.tail));
-    //     self.tail = tail;
-    // }
+    /// Moves all elements of `other` right after `pos` in O(1), leaving `other` empty.
+    ///
+    /// Indexers to the elements of `other` stay valid for `self`.
+    ///
+    /// # Panics
+    /// Panics if `pos` does not belong to this list.
+    pub fn splice_next(&mut self, pos: &Indexer<T>, other: &mut Self) {
+        self.check(pos);
+        self.id.absorb(&mut other.id);
+        let (Some(head), Some(tail)) = (other.head.take(), other.tail.take()) else {
+            return;
+        };
+        if self.is_tail(pos) {
+            self.tail = Some(Rc::clone(&tail));
+        } else {
+            let next = pos.0.next.borrow().upgrade().unwrap();
+            tail.link_next(Rc::downgrade(&next));
+            next.link_prev(tail);
+        }
+        pos.0.link_next(Rc::downgrade(&head));
+        head.link_prev(Rc::clone(&pos.0));
+        self.size += std::mem::take(&mut other.size);
+    }
 
     pub const fn len(&self) -> usize {
         self.size
     }
+
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Iterates over the elements from the first to the last.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head.as_ref().map(Rc::as_ptr),
+            back: self.tail.as_ref().map(Rc::as_ptr),
+            head: self.head.as_ref().map(Rc::as_ptr),
+            tail: self.tail.as_ref().map(Rc::as_ptr),
+            len: self.size,
+            marker: PhantomData,
+        }
+    }
+
+    /// Iterates once around the list, starting at `pos` and wrapping from the last element to
+    /// the first.
+    ///
+    /// # Panics
+    /// Panics if `pos` does not belong to this list.
+    pub fn iter_from<'a>(&'a self, pos: &'a Indexer<T>) -> Iter<'a, T> {
+        self.check(pos);
+        let back = pos.0.prev.borrow().as_ref().map(Rc::as_ptr);
+        Iter {
+            front: Some(Rc::as_ptr(&pos.0)),
+            back: back.or(self.tail.as_ref().map(Rc::as_ptr)),
+            head: self.head.as_ref().map(Rc::as_ptr),
+            tail: self.tail.as_ref().map(Rc::as_ptr),
+            len: self.size,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for CyclicList<T> {
+    fn default() -> Self {
+        Self {
+            head: None,
+            tail: None,
+            size: 0,
+            id: Identity::new(),
+        }
+    }
+}
+
+impl<T> Drop for CyclicList<T> {
+    fn drop(&mut self) {
+        // unlink from the back, so long lists are not dropped recursively
+        self.head = None;
+        let mut node = self.tail.take();
+        while let Some(n) = node {
+            node = n.prev.borrow_mut().take();
+        }
+    }
+}
+
+impl<T> FromIterator<T> for CyclicList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::default();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for CyclicList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for key in iter {
+            self.push_back(key);
+        }
+    }
+}
+
+/// Iterator over the elements of a [`CyclicList`], created by [`CyclicList::iter`] and
+/// [`CyclicList::iter_from`].
+pub struct Iter<'a, T> {
+    front: Option<*const Node<T>>,
+    back: Option<*const Node<T>>,
+    head: Option<*const Node<T>>,
+    tail: Option<*const Node<T>>,
+    len: usize,
+    marker: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // the list is borrowed, so every node reachable from it stays alive
+        let node = unsafe { &*self.front? };
+        let next = node.next.borrow().upgrade();
+        self.front = next.as_ref().map(Rc::as_ptr).or(self.head);
+        Some(&node.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let node = unsafe { &*self.back? };
+        self.back = node.prev.borrow().as_ref().map(Rc::as_ptr).or(self.tail);
+        Some(&node.key)
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self { ..*self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CyclicList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
 }
 
 use std::fmt;
@@ -181,10 +467,8 @@ use std::fmt;
 impl<T: fmt::Display> fmt::Debug for CyclicList<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "[")?;
-        let mut node = Indexer(Rc::clone(&self.head));
-        for _ in 0..self.size {
-            write!(f, "{}, ", node.0.key)?;
-            node = self.next(&node);
+        for key in self {
+            write!(f, "{}, ", key)?;
         }
         write!(f, "]")
     }
@@ -209,7 +493,83 @@ mod test {
         list.insert_prev(&iter, 15);
         println!("{:?}", &list);
         let iter2 = list.delete(&iter);
+        assert_eq!(iter2.map(|i| *i.key()), Some(11));
         println!("{:?}", &list);
         println!("{}", Indexer::into_key(iter).unwrap_or_default());
     }
+
+    #[test]
+    fn collection_test() {
+        let mut list: CyclicList<i32> = (1..=3).collect();
+        let mut other: CyclicList<i32> = (4..=5).collect();
+        list.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            [5, 4, 3, 2, 1]
+        );
+
+        let mut pos = list.take_one();
+        pos = list.next(&list.next(&pos));
+        let mut middle: CyclicList<i32> = [30, 31].into_iter().collect();
+        list.splice_next(&pos, &mut middle);
+        assert_eq!(
+            list.iter_from(&pos).copied().collect::<Vec<_>>(),
+            [3, 30, 31, 4, 5, 1, 2]
+        );
+        assert_eq!(list.iter_from(&pos).next_back(), Some(&2));
+
+        let next = list.next(&pos);
+        assert!(list.remove(pos.clone()).is_err());
+        assert_eq!(list.remove(next).ok(), Some(30));
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), [1, 2, 31, 4, 5]);
+
+        let mut single = CyclicList::new(7);
+        let only = single.take_one();
+        assert!(single.delete(&only).is_none());
+        assert!(single.is_empty());
+        assert_eq!(Indexer::into_key(only).ok(), Some(7));
+        single.push_front(8);
+        assert_eq!((single.front(), single.back()), (Some(&8), Some(&8)));
+    }
+
+    #[test]
+    fn foreign_indexer_test() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let a: CyclicList<String> = ["x", "y", "z"].map(String::from).into_iter().collect();
+        let mut b: CyclicList<String> = ["w"].map(String::from).into_iter().collect();
+        let y = a.next(&a.take_one());
+        let mut it = a.iter();
+        assert_eq!(it.next().map(String::as_str), Some("x"));
+        // unlinking `y` through `b` would free it under the iterator of `a`
+        let result = catch_unwind(AssertUnwindSafe(|| b.delete(&y)));
+        assert!(result.is_err());
+        drop(y);
+        assert_eq!(it.next().map(String::as_str), Some("y"));
+        assert_eq!(it.next().map(String::as_str), Some("z"));
+        assert_eq!(b.len(), 1);
+
+        let mut a = a;
+        let w = b.take_one();
+        assert!(!a.owns(&w));
+        let removed = a.take_one();
+        a.delete(&removed);
+        assert!(!a.owns(&removed));
+        assert!(catch_unwind(AssertUnwindSafe(|| a.insert_next(&removed, "v".into()))).is_err());
+
+        // indexers follow their elements when lists are spliced together
+        a.append(&mut b);
+        assert!(a.owns(&w));
+        a.insert_prev(&w, "v".into());
+        assert_eq!(
+            a.iter().map(String::as_str).collect::<Vec<_>>(),
+            ["y", "z", "v", "w"]
+        );
+        let mut empty = CyclicList::default();
+        empty.append(&mut a);
+        assert!(empty.owns(&w) && !a.owns(&w));
+    }
 }
@@ -1,52 +1,599 @@
-use super::cycliclist::{CyclicList, Indexer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
 
-#[allow(dead_code)]
-struct Node {
-    children: Vec<Node>,
-    key: i32,
+use super::compare::{Compare, MinOrder};
+use super::handle::{Identity, Slot, Ticket};
+use super::{AddressableHeap, Heap, HeapError, MeldableHeap};
+
+struct Node<K> {
+    key: K,
+    /// Siblings form a circular doubly linked list; a lone node points to itself.
+    next: NodePtr<K>,
+    prev: NodePtr<K>,
+    child: Option<NodePtr<K>>,
+    parent: Option<NodePtr<K>>,
+    degree: usize,
+    /// Set when the node has lost a child since it was linked under its parent.
+    mark: bool,
+    /// Set only for nodes inserted with a handle.
+    slot: Option<Slot<Node<K>>>,
 }
 
-#[allow(dead_code)]
-struct FibonacciHeap {
-    min: Option<(CyclicList<Node>, Indexer<Node>)>,
+type NodePtr<K> = NonNull<Node<K>>;
+
+/// Fibonacci heap: O(1) insert, meld and amortized decrease_key, O(log n) amortized delete_min.
+pub struct FibonacciHeap<K, C = MinOrder> {
+    min: Option<NodePtr<K>>,
     size: usize,
+    cmp: C,
+    id: Identity,
+    marker: PhantomData<Box<Node<K>>>,
 }
 
-#[allow(dead_code)]
-impl Node {
-    const fn new(key: i32) -> Self {
-        Self {
-            children: Vec::new(),
+/// Points to an element inserted with [`FibonacciHeap::insert_with_handle`].
+///
+/// Its element never leaves its node, which cascading cuts only move to the root list. Uses are
+/// checked as described on [`AddressableHeap`].
+pub struct Handle<K>(Ticket<Node<K>>);
+
+impl<K> Clone for Handle<K> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K> PartialEq for Handle<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K> Eq for Handle<K> {}
+
+impl<K> fmt::Debug for Handle<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.0).finish()
+    }
+}
+
+impl<K> Node<K> {
+    fn new_ptr(key: K) -> NodePtr<K> {
+        let node = NonNull::from(Box::leak(Box::new(Self {
             key,
+            next: NonNull::dangling(),
+            prev: NonNull::dangling(),
+            child: None,
+            parent: None,
+            degree: 0,
+            mark: false,
+            slot: None,
+        })));
+        unsafe {
+            (*node.as_ptr()).next = node;
+            (*node.as_ptr()).prev = node;
         }
+        node
     }
 
-    fn key(&self) -> &i32 {
-        &self.key
+    /// Inserts the circular list of `b` right after `a`.
+    unsafe fn splice(a: NodePtr<K>, b: NodePtr<K>) {
+        let a_next = (*a.as_ptr()).next;
+        let b_prev = (*b.as_ptr()).prev;
+        (*a.as_ptr()).next = b;
+        (*b.as_ptr()).prev = a;
+        (*b_prev.as_ptr()).next = a_next;
+        (*a_next.as_ptr()).prev = b_prev;
+    }
+
+    /// Removes `node` from its sibling list, leaving it alone in its own list.
+    unsafe fn unlink(node: NodePtr<K>) {
+        let next = (*node.as_ptr()).next;
+        let prev = (*node.as_ptr()).prev;
+        (*prev.as_ptr()).next = next;
+        (*next.as_ptr()).prev = prev;
+        (*node.as_ptr()).next = node;
+        (*node.as_ptr()).prev = node;
+    }
+
+    /// nodeを指すポインタが残っていてはならない
+    fn into_key(node: NodePtr<K>) -> K {
+        unsafe { Box::from_raw(node.as_ptr()).key }
     }
 }
 
-#[allow(dead_code)]
-impl FibonacciHeap {
+impl<K> FibonacciHeap<K> {
     pub const fn new() -> Self {
-        Self { min: None, size: 0 }
+        Self::with_compare(MinOrder)
+    }
+}
+
+impl<K, C> FibonacciHeap<K, C> {
+    pub const fn with_compare(cmp: C) -> Self {
+        Self {
+            min: None,
+            size: 0,
+            cmp,
+            id: Identity::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn compare(&self) -> &C {
+        &self.cmp
+    }
+
+    pub const fn len(&self) -> usize {
+        self.size
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
     }
 
-    pub fn insert(&mut self, key: i32) {
-        self.add_root(Node::new(key));
+    /// Removes all elements.
+    pub fn clear(&mut self) {
+        let Some(min) = self.min.take() else {
+            return;
+        };
+        self.size = 0;
+        // trees can degenerate into long chains, so walk them with an explicit stack
+        let mut stack = vec![min];
+        while let Some(first) = stack.pop() {
+            let mut p = first;
+            loop {
+                let next = unsafe { (*p.as_ptr()).next };
+                if let Some(child) = unsafe { (*p.as_ptr()).child } {
+                    stack.push(child);
+                }
+                drop(Node::into_key(p));
+                if next == first {
+                    break;
+                }
+                p = next;
+            }
+        }
+    }
+}
+
+impl<K, C: Default> Default for FibonacciHeap<K, C> {
+    fn default() -> Self {
+        Self::with_compare(C::default())
+    }
+}
+
+impl<K, C: Compare<K>> FibonacciHeap<K, C> {
+    pub fn insert(&mut self, key: K) {
+        self.push_root(Node::new_ptr(key));
+    }
+
+    /// Inserts `key` and returns a handle to it for [`decrease_key`](Self::decrease_key).
+    pub fn insert_with_handle(&mut self, key: K) -> Handle<K> {
+        let node = Node::new_ptr(key);
+        let (slot, ticket) = Ticket::issue(&mut self.id, node);
+        unsafe { (*node.as_ptr()).slot = Some(slot) };
+        self.push_root(node);
+        Handle(ticket)
+    }
+
+    /// Adds a lone node to the root list.
+    fn push_root(&mut self, node: NodePtr<K>) {
+        if let Some(min) = self.min {
+            unsafe {
+                Node::splice(min, node);
+                if self.cmp.lt(&(*node.as_ptr()).key, &(*min.as_ptr()).key) {
+                    self.min = Some(node);
+                }
+            }
+        } else {
+            self.min = Some(node);
+        }
         self.size += 1;
     }
 
-    fn add_root(&mut self, root: Node) {
-        if let Some((l, i)) = &mut self.min {
-            let i2 = l.insert_next(i, root);
-            if i.key().key() > i2.key().key() {
-                *i = i2;
+    pub fn peek(&self) -> Option<&K> {
+        self.min.map(|min| unsafe { &(*min.as_ptr()).key })
+    }
+
+    /// Removes the minimum, moving its children to the root list and consolidating it,
+    /// in O(log n) amortized.
+    pub fn delete_min(&mut self) -> Option<K> {
+        self.pop_min().map(Node::into_key)
+    }
+
+    /// Detaches the minimum node and returns it with all links cleared.
+    fn pop_min(&mut self) -> Option<NodePtr<K>> {
+        let min = self.min?;
+        unsafe {
+            if let Some(first) = (*min.as_ptr()).child.take() {
+                let mut p = first;
+                loop {
+                    (*p.as_ptr()).parent = None;
+                    (*p.as_ptr()).mark = false;
+                    p = (*p.as_ptr()).next;
+                    if p == first {
+                        break;
+                    }
+                }
+                Node::splice(min, first);
             }
+            let next = (*min.as_ptr()).next;
+            Node::unlink(min);
+            (*min.as_ptr()).degree = 0;
+            self.min = (next != min).then_some(next);
+        }
+        self.size -= 1;
+        self.consolidate();
+        Some(min)
+    }
+
+    /// Links roots of equal degree until every root has a distinct degree, and finds the
+    /// new minimum.
+    fn consolidate(&mut self) {
+        let Some(start) = self.min else {
+            return;
+        };
+        let mut roots = Vec::new();
+        let mut p = start;
+        loop {
+            roots.push(p);
+            p = unsafe { (*p.as_ptr()).next };
+            if p == start {
+                break;
+            }
+        }
+        let mut table: Vec<Option<NodePtr<K>>> = Vec::new();
+        for mut x in roots {
+            loop {
+                let degree = unsafe { (*x.as_ptr()).degree };
+                if table.len() <= degree {
+                    table.resize(degree + 1, None);
+                }
+                match table[degree].take() {
+                    Some(y) => x = unsafe { self.link(x, y) },
+                    None => {
+                        table[degree] = Some(x);
+                        break;
+                    }
+                }
+            }
+        }
+        self.min = table.into_iter().flatten().reduce(|a, b| unsafe {
+            if self.cmp.lt(&(*b.as_ptr()).key, &(*a.as_ptr()).key) {
+                b
+            } else {
+                a
+            }
+        });
+    }
+
+    /// Makes the greater of the roots `x` and `y` a child of the other and returns the new root.
+    unsafe fn link(&self, x: NodePtr<K>, y: NodePtr<K>) -> NodePtr<K> {
+        let (root, child) = if self.cmp.gt(&(*x.as_ptr()).key, &(*y.as_ptr()).key) {
+            (y, x)
         } else {
-            let list = CyclicList::new(root);
-            let i = list.take_one();
-            self.min = Some((list, i));
+            (x, y)
+        };
+        Node::unlink(child);
+        (*child.as_ptr()).parent = Some(root);
+        (*child.as_ptr()).mark = false;
+        match (*root.as_ptr()).child {
+            Some(first) => Node::splice(first, child),
+            None => (*root.as_ptr()).child = Some(child),
+        }
+        (*root.as_ptr()).degree += 1;
+        root
+    }
+
+    /// Moves `node` from its parent's children to the root list.
+    unsafe fn cut(&mut self, node: NodePtr<K>) {
+        let parent = (*node.as_ptr()).parent.take().unwrap();
+        if (*parent.as_ptr()).child == Some(node) {
+            let next = (*node.as_ptr()).next;
+            (*parent.as_ptr()).child = (next != node).then_some(next);
+        }
+        Node::unlink(node);
+        (*parent.as_ptr()).degree -= 1;
+        (*node.as_ptr()).mark = false;
+        Node::splice(self.min.unwrap(), node);
+    }
+
+    /// Marks `node` after it lost a child, or cuts it too if it had already lost one.
+    unsafe fn cascading_cut(&mut self, mut node: NodePtr<K>) {
+        while let Some(parent) = (*node.as_ptr()).parent {
+            if !(*node.as_ptr()).mark {
+                (*node.as_ptr()).mark = true;
+                return;
+            }
+            self.cut(node);
+            node = parent;
+        }
+    }
+
+    /// Replaces the key of `handle` with `key`, which must not be greater.
+    ///
+    /// If the key becomes smaller than its parent's, the node is cut to the root list, followed
+    /// by cascading cuts of marked ancestors, in O(1) amortized.
+    ///
+    /// # Panics
+    /// Panics if `key` is greater than the current key.
+    pub fn decrease_key(&mut self, handle: &Handle<K>, key: K) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.decrease_node(node, key) };
+        Ok(())
+    }
+
+    /// Removes the element of `handle` and returns it.
+    ///
+    /// The node is cut to the root list and removed like the minimum, in O(log n) amortized.
+    pub fn remove(&mut self, handle: Handle<K>) -> Result<K, HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { Ok(Node::into_key(self.remove_node(node))) }
+    }
+
+    /// Replaces the key of `handle` with `key`, which must not be less.
+    ///
+    /// The node is removed and inserted back with the new key, in O(log n) amortized.
+    ///
+    /// # Panics
+    /// Panics if `key` is less than the current key.
+    pub fn increase_key(&mut self, handle: &Handle<K>, key: K) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.increase_node(node, key) };
+        Ok(())
+    }
+
+    /// Replaces the key of `handle` with `f(key)`, calling [`decrease_key`](Self::decrease_key)
+    /// or [`increase_key`](Self::increase_key) depending on the direction of the change.
+    pub fn update<F: FnOnce(&K) -> K>(
+        &mut self,
+        handle: &Handle<K>,
+        f: F,
+    ) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.update_node(node, f) };
+        Ok(())
+    }
+
+    unsafe fn decrease_node(&mut self, node: NodePtr<K>, key: K) {
+        assert!(
+            !self.cmp.gt(&key, &(*node.as_ptr()).key),
+            "decrease_key: new key is greater than the current one"
+        );
+        (*node.as_ptr()).key = key;
+        if let Some(parent) = (*node.as_ptr()).parent {
+            if self.cmp.lt(&(*node.as_ptr()).key, &(*parent.as_ptr()).key) {
+                self.cut(node);
+                self.cascading_cut(parent);
+            }
+        }
+        let min = self.min.unwrap();
+        if self.cmp.lt(&(*node.as_ptr()).key, &(*min.as_ptr()).key) {
+            self.min = Some(node);
+        }
+    }
+
+    unsafe fn increase_node(&mut self, node: NodePtr<K>, key: K) {
+        assert!(
+            !self.cmp.lt(&key, &(*node.as_ptr()).key),
+            "increase_key: new key is less than the current one"
+        );
+        let node = self.remove_node(node);
+        (*node.as_ptr()).key = key;
+        self.push_root(node);
+    }
+
+    unsafe fn update_node<F: FnOnce(&K) -> K>(&mut self, node: NodePtr<K>, f: F) {
+        let key = f(&(*node.as_ptr()).key);
+        match self.cmp.compare(&key, &(*node.as_ptr()).key) {
+            Ordering::Less => self.decrease_node(node, key),
+            Ordering::Greater => self.increase_node(node, key),
+            Ordering::Equal => (*node.as_ptr()).key = key,
+        }
+    }
+
+    /// Detaches `node` from the heap and returns it with all links cleared.
+    unsafe fn remove_node(&mut self, node: NodePtr<K>) -> NodePtr<K> {
+        if let Some(parent) = (*node.as_ptr()).parent {
+            self.cut(node);
+            self.cascading_cut(parent);
+        }
+        // as if its key had been decreased below every other key
+        self.min = Some(node);
+        self.pop_min().unwrap()
+    }
+
+    /// Moves all elements of `other` into `self` in O(1).
+    pub fn meld(&mut self, mut other: Self) {
+        self.append(&mut other);
+    }
+
+    /// Moves all elements of `other` into `self` in O(1), leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        if let Some(b) = other.min.take() {
+            match self.min {
+                Some(a) => unsafe {
+                    Node::splice(a, b);
+                    if self.cmp.lt(&(*b.as_ptr()).key, &(*a.as_ptr()).key) {
+                        self.min = Some(b);
+                    }
+                },
+                None => self.min = Some(b),
+            }
+            self.size += std::mem::take(&mut other.size);
+        }
+        self.id.absorb(&mut other.id);
+    }
+}
+
+impl<K, C: Compare<K>> Heap for FibonacciHeap<K, C> {
+    type Item = K;
+
+    fn insert(&mut self, item: K) {
+        Self::insert(self, item);
+    }
+
+    fn delete_min(&mut self) -> Option<K> {
+        Self::delete_min(self)
+    }
+
+    fn peek(&self) -> Option<&K> {
+        Self::peek(self)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+impl<K, C: Compare<K>> MeldableHeap for FibonacciHeap<K, C> {
+    fn meld(&mut self, other: Self) {
+        Self::meld(self, other);
+    }
+}
+
+impl<K, C: Compare<K>> AddressableHeap for FibonacciHeap<K, C> {
+    type Handle = Handle<K>;
+
+    fn insert_with_handle(&mut self, item: K) -> Handle<K> {
+        Self::insert_with_handle(self, item)
+    }
+
+    fn decrease_key(&mut self, handle: &Handle<K>, item: K) -> Result<(), HeapError> {
+        Self::decrease_key(self, handle, item)
+    }
+
+    fn remove(&mut self, handle: Handle<K>) -> Result<K, HeapError> {
+        Self::remove(self, handle)
+    }
+
+    fn increase_key(&mut self, handle: &Handle<K>, item: K) -> Result<(), HeapError> {
+        Self::increase_key(self, handle, item)
+    }
+
+    fn update<F: FnOnce(&K) -> K>(&mut self, handle: &Handle<K>, f: F) -> Result<(), HeapError> {
+        Self::update(self, handle, f)
+    }
+}
+
+impl<K, C: Compare<K> + Default> FromIterator<K> for FibonacciHeap<K, C> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut heap = Self::default();
+        heap.extend(iter);
+        heap
+    }
+}
+
+impl<K, C: Compare<K>> Extend<K> for FibonacciHeap<K, C> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|key| self.insert(key));
+    }
+}
+
+impl<K: Clone> Node<K> {
+    /// Copies the forest whose root list contains `first`, returning the copy of `first`.
+    fn clone_forest(first: NodePtr<K>) -> NodePtr<K> {
+        let mut root = None;
+        let mut stack = vec![(first, None)];
+        while let Some((first, parent)) = stack.pop() {
+            let mut head: Option<NodePtr<K>> = None;
+            let mut p = first;
+            loop {
+                unsafe {
+                    let node = Self::new_ptr((*p.as_ptr()).key.clone());
+                    (*node.as_ptr()).degree = (*p.as_ptr()).degree;
+                    (*node.as_ptr()).mark = (*p.as_ptr()).mark;
+                    (*node.as_ptr()).parent = parent;
+                    match head {
+                        Some(head) => Self::splice((*head.as_ptr()).prev, node),
+                        None => head = Some(node),
+                    }
+                    if let Some(child) = (*p.as_ptr()).child {
+                        stack.push((child, Some(node)));
+                    }
+                    p = (*p.as_ptr()).next;
+                }
+                if p == first {
+                    break;
+                }
+            }
+            match parent {
+                Some(parent) => unsafe { (*parent.as_ptr()).child = head },
+                None => root = head,
+            }
+        }
+        root.unwrap()
+    }
+}
+
+impl<K: Clone, C: Clone> Clone for FibonacciHeap<K, C> {
+    fn clone(&self) -> Self {
+        Self {
+            min: self.min.map(Node::clone_forest),
+            size: self.size,
+            cmp: self.cmp.clone(),
+            id: Identity::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, C> Drop for FibonacciHeap<K, C> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::heap::compare::MaxOrder;
+
+    #[test]
+    fn heaptest() {
+        let mut heap = FibonacciHeap::new();
+        for i in [5, 3, 8, 1, 9, 2, 7] {
+            heap.insert(i);
+        }
+        assert_eq!(heap.peek(), Some(&1));
+        let mut other: FibonacciHeap<_> = (10..20).rev().collect();
+        other.delete_min();
+        heap.meld(other);
+        let clone = heap.clone();
+        let mut v = Vec::new();
+        while let Some(x) = heap.delete_min() {
+            v.push(x);
+        }
+        assert_eq!(v, [1, 2, 3, 5, 7, 8, 9, 11, 12, 13, 14, 15, 16, 17, 18, 19]);
+        assert_eq!(clone.len(), 16);
+
+        let mut max = FibonacciHeap::with_compare(MaxOrder);
+        max.extend([1, 3, 2]);
+        assert_eq!(max.delete_min(), Some(3));
+    }
+
+    #[test]
+    fn cascading_cut_test() {
+        let mut heap = FibonacciHeap::new();
+        let handles: Vec<_> = (0..64).map(|i| heap.insert_with_handle(i)).collect();
+        heap.delete_min();
+        // cut many nodes out of the deep trees so that marked parents get cut as well
+        for (i, h) in handles.iter().enumerate().skip(1).rev().step_by(2) {
+            heap.decrease_key(h, i as i32 - 100).unwrap();
+        }
+        let clone = heap.clone();
+        let mut expected: Vec<i32> = (1..64)
+            .map(|i| if i % 2 == 1 { i - 100 } else { i })
+            .collect();
+        expected.sort();
+        for heap in [heap, clone] {
+            let mut heap = heap;
+            let mut v = Vec::new();
+            while let Some(x) = heap.delete_min() {
+                v.push(x);
+            }
+            assert_eq!(v, expected);
         }
     }
 }
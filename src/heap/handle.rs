@@ -0,0 +1,136 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use super::HeapError;
+
+/// Identity of a heap that handles are checked against.
+///
+/// When a heap is melded into another, its owner is forwarded to the owner of the surviving heap,
+/// so the handles of both heaps stay valid without touching them.
+#[derive(Default)]
+pub(super) struct Owner {
+    forward: RefCell<Option<Rc<Owner>>>,
+}
+
+impl Owner {
+    /// Follows the forwarding chain of `this` and shortens it to a single step.
+    fn root(this: &Rc<Self>) -> Rc<Self> {
+        let mut root = this.clone();
+        loop {
+            let next = root.forward.borrow().clone();
+            match next {
+                Some(next) => root = next,
+                None => break,
+            }
+        }
+        let mut p = this.clone();
+        while !Rc::ptr_eq(&p, &root) {
+            let next = p.forward.replace(Some(root.clone())).unwrap();
+            p = next;
+        }
+        root
+    }
+}
+
+/// Owner of a heap, created when the first handle is handed out.
+pub(super) struct Identity(Option<Rc<Owner>>);
+
+impl Identity {
+    pub(super) const fn new() -> Self {
+        Self(None)
+    }
+
+    pub(super) fn get(&mut self) -> Rc<Owner> {
+        self.0.get_or_insert_with(Default::default).clone()
+    }
+
+    /// Makes the handles of `other` valid for `self` and leaves `other` with a fresh identity.
+    pub(super) fn absorb(&mut self, other: &mut Self) {
+        if let Some(owner) = other.0.take() {
+            match &self.0 {
+                Some(root) => *owner.forward.borrow_mut() = Some(root.clone()),
+                None => self.0 = Some(owner),
+            }
+        }
+    }
+
+    pub(super) fn owns(&self, owner: &Rc<Owner>) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|root| Rc::ptr_eq(root, &Owner::root(owner)))
+    }
+}
+
+/// Location of a handled element, held by the node that currently stores the element.
+///
+/// Dropping the slot together with its node marks every handle to the element as stale.
+pub(super) struct Slot<N>(Rc<Cell<Option<NonNull<N>>>>);
+
+impl<N> Slot<N> {
+    pub(super) fn set(&self, node: NonNull<N>) {
+        self.0.set(Some(node));
+    }
+}
+
+impl<N> Drop for Slot<N> {
+    fn drop(&mut self) {
+        self.0.set(None);
+    }
+}
+
+/// Checked reference to an element, wrapped by the `Handle` of each heap.
+pub(super) struct Ticket<N> {
+    node: Rc<Cell<Option<NonNull<N>>>>,
+    owner: Rc<Owner>,
+}
+
+impl<N> Ticket<N> {
+    /// Creates the slot for `node` and a ticket to it issued by `heap`.
+    pub(super) fn issue(heap: &mut Identity, node: NonNull<N>) -> (Slot<N>, Self) {
+        let cell = Rc::new(Cell::new(Some(node)));
+        let ticket = Self {
+            node: cell.clone(),
+            owner: heap.get(),
+        };
+        (Slot(cell), ticket)
+    }
+
+    /// Returns the node of the element if it is still in `heap`.
+    pub(super) fn node(&self, heap: &Identity) -> Result<NonNull<N>, HeapError> {
+        let node = self.node.get().ok_or(HeapError::StaleHandle)?;
+        if heap.owns(&self.owner) {
+            Ok(node)
+        } else {
+            Err(HeapError::ForeignHandle)
+        }
+    }
+}
+
+impl<N> Clone for Ticket<N> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node.clone(),
+            owner: self.owner.clone(),
+        }
+    }
+}
+
+impl<N> PartialEq for Ticket<N> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.node, &other.node)
+    }
+}
+
+impl<N> Eq for Ticket<N> {}
+
+impl<N> fmt::Debug for Ticket<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Ticket").field(&self.node.get()).finish()
+    }
+}
+
+/// Invariant lifetime that brands the handles of one scope, so they cannot be used in another.
+pub(super) type Brand<'id> = PhantomData<fn(&'id ()) -> &'id ()>;
@@ -1,39 +1,398 @@
-use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
 
-#[allow(dead_code)]
 struct Node<T> {
     value: T,
-    next: Option<RefCell<T>>,
+    next: Option<NonNull<Node<T>>>,
 }
 
-#[allow(dead_code)]
-struct List<T> {
-    head: Option<RefCell<T>>,
-    tail: Option<RefCell<T>>,
+/// Singly linked list that keeps a pointer to its last node, so both ends can be pushed and
+/// another list can be appended in O(1).
+pub struct List<T> {
+    head: Option<NonNull<Node<T>>>,
+    tail: Option<NonNull<Node<T>>>,
     size: usize,
+    marker: PhantomData<Box<Node<T>>>,
 }
 
-struct Indexer<T> {
-    pos: RefCell<T>,
+/// Cursor over a [`List`] that can edit the list around its position.
+///
+/// Besides the elements, the cursor can point to the end of the list, after the last element.
+/// Moving forward from the end wraps around to the first element.
+pub struct Indexer<'a, T> {
+    list: &'a mut List<T>,
+    /// Node before `pos`, or `None` if `pos` is the first node.
+    prev: Option<NonNull<Node<T>>>,
+    pos: Option<NonNull<Node<T>>>,
 }
-#[allow(dead_code)]
+
 impl<T> Node<T> {
     const fn new(value: T) -> Self {
         Self { value, next: None }
     }
+
+    fn new_ptr(value: T) -> NonNull<Self> {
+        NonNull::from(Box::leak(Box::new(Self::new(value))))
+    }
+
+    fn into_value(node: NonNull<Self>) -> T {
+        unsafe { Box::from_raw(node.as_ptr()).value }
+    }
 }
 
-#[allow(dead_code)]
 impl<T> List<T> {
-    const fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             head: None,
             tail: None,
             size: 0,
+            marker: PhantomData,
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.size
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        let node = Node::new_ptr(value);
+        unsafe {
+            (*node.as_ptr()).next = self.head;
+        }
+        if self.tail.is_none() {
+            self.tail = Some(node);
+        }
+        self.head = Some(node);
+        self.size += 1;
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        let node = Node::new_ptr(value);
+        match self.tail {
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(node) },
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.size += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let head = self.head?;
+        unsafe {
+            self.head = (*head.as_ptr()).next;
+        }
+        if self.head.is_none() {
+            self.tail = None;
+        }
+        self.size -= 1;
+        Some(Node::into_value(head))
+    }
+
+    /// Removes the last element. This walks the list to find the new last node, so it takes O(n).
+    pub fn pop_back(&mut self) -> Option<T> {
+        let mut cursor = self.indexer();
+        while cursor.peek_next().is_some() {
+            cursor.move_next();
+        }
+        cursor.remove_current()
+    }
+
+    /// Moves all elements of `other` to the end of `self` in O(1), leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        let Some(head) = other.head.take() else {
+            return;
+        };
+        match self.tail {
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(head) },
+            None => self.head = Some(head),
+        }
+        self.tail = other.tail.take();
+        self.size += std::mem::take(&mut other.size);
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    /// Returns a cursor at the first element, or at the end if the list is empty.
+    pub fn indexer(&mut self) -> Indexer<'_, T> {
+        Indexer {
+            pos: self.head,
+            prev: None,
+            list: self,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            head: self.head,
+            len: self.size,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            head: self.head,
+            len: self.size,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Indexer<'_, T> {
+    /// Returns the element at the cursor, or `None` at the end of the list.
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.pos.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// Returns the element after the cursor. At the end of the list this is the first element.
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = match self.pos {
+            Some(pos) => unsafe { (*pos.as_ptr()).next },
+            None => self.list.head,
+        };
+        next.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// Moves to the next element, or from the last element to the end of the list.
+    pub fn move_next(&mut self) {
+        match self.pos {
+            Some(pos) => {
+                self.prev = Some(pos);
+                self.pos = unsafe { (*pos.as_ptr()).next };
+            }
+            None => {
+                self.prev = None;
+                self.pos = self.list.head;
+            }
+        }
+    }
+
+    /// Inserts `value` before the cursor, which keeps pointing to the same element.
+    pub fn insert_before(&mut self, value: T) {
+        let node = Node::new_ptr(value);
+        unsafe {
+            (*node.as_ptr()).next = self.pos;
+            match self.prev {
+                Some(prev) => (*prev.as_ptr()).next = Some(node),
+                None => self.list.head = Some(node),
+            }
+        }
+        if self.pos.is_none() {
+            self.list.tail = Some(node);
+        }
+        self.prev = Some(node);
+        self.list.size += 1;
+    }
+
+    /// Inserts `value` after the cursor. At the end of the list, it becomes the first element.
+    pub fn insert_after(&mut self, value: T) {
+        match self.pos {
+            Some(pos) => {
+                let node = Node::new_ptr(value);
+                unsafe {
+                    (*node.as_ptr()).next = (*pos.as_ptr()).next;
+                    (*pos.as_ptr()).next = Some(node);
+                }
+                if self.list.tail == Some(pos) {
+                    self.list.tail = Some(node);
+                }
+                self.list.size += 1;
+            }
+            None => {
+                self.list.push_front(value);
+                if self.prev.is_none() {
+                    // an empty list now has a first element, and the end follows it
+                    self.prev = self.list.head;
+                }
+            }
+        }
+    }
+
+    /// Removes the element at the cursor and moves to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.pos?;
+        unsafe {
+            self.pos = (*node.as_ptr()).next;
+            match self.prev {
+                Some(prev) => (*prev.as_ptr()).next = self.pos,
+                None => self.list.head = self.pos,
+            }
+        }
+        if self.list.tail == Some(node) {
+            self.list.tail = self.prev;
+        }
+        self.list.size -= 1;
+        Some(Node::into_value(node))
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+/// Iterator over the elements of a [`List`] from front to back.
+pub struct Iter<'a, T> {
+    head: Option<NonNull<Node<T>>>,
+    len: usize,
+    marker: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.head?;
+        unsafe {
+            self.head = (*node.as_ptr()).next;
+            self.len -= 1;
+            Some(&(*node.as_ptr()).value)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            head: self.head,
+            len: self.len,
+            marker: PhantomData,
         }
     }
+}
 
-    fn push_back(&mut self, value: T) {}
+/// Mutable iterator over the elements of a [`List`] from front to back.
+pub struct IterMut<'a, T> {
+    head: Option<NonNull<Node<T>>>,
+    len: usize,
+    marker: PhantomData<&'a mut Node<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        let node = self.head?;
+        unsafe {
+            self.head = (*node.as_ptr()).next;
+            self.len -= 1;
+            Some(&mut (*node.as_ptr()).value)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+/// Owning iterator over the elements of a [`List`] from front to back.
+pub struct IntoIter<T>(List<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
 }
 
 #[cfg(test)]
@@ -43,15 +402,11 @@ mod test {
     use std::ptr::NonNull;
     #[test]
     fn ptr_test() {
-        let ptr;
-        {
-            let mut x = [100, 200, 300];
-            ptr = &mut x as *mut i32;
-        }
+        let mut x = [100, 200, 300];
+        let ptr = &mut x as *mut i32;
         let np = NonNull::new(ptr).unwrap();
-        let a = [20, 30, 40, 50, 60];
         unsafe {
-            println!("{}", *(np.as_ptr().wrapping_add(1)));
+            assert_eq!(*(np.as_ptr().wrapping_add(1)), 200);
         }
     }
 
@@ -71,4 +426,60 @@ mod test {
     // fn lifetime_test() {
     //     let l
     // }
+
+    #[test]
+    fn list_test() {
+        let mut list = List::new();
+        list.push_back(2);
+        list.push_back(3);
+        list.push_front(1);
+        let mut other: List<_> = (4..=6).collect();
+        list.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(list.len(), 6);
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&6)));
+        assert_eq!(list.pop_back(), Some(6));
+        assert_eq!(list.pop_front(), Some(1));
+        list.iter_mut().for_each(|x| *x *= 10);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), [20, 30, 40, 50]);
+        assert_eq!(format!("{:?}", list.clone()), "[20, 30, 40, 50]");
+
+        other.append(&mut list);
+        other.push_back(60);
+        assert_eq!(other.into_iter().collect::<Vec<_>>(), [20, 30, 40, 50, 60]);
+        assert_eq!(list.pop_back(), None);
+    }
+
+    #[test]
+    fn indexer_test() {
+        let mut list: List<i32> = (1..=5).collect();
+        let mut cursor = list.indexer();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(2));
+        cursor.insert_before(20);
+        cursor.insert_after(30);
+        assert_eq!(cursor.current(), Some(&mut 3));
+        cursor.move_next();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(5));
+        assert_eq!(cursor.current(), None);
+        cursor.insert_before(50);
+        cursor.insert_after(0);
+        assert_eq!(cursor.peek_next(), Some(&mut 0));
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(0));
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            [1, 20, 3, 30, 4, 50]
+        );
+        assert_eq!(list.back(), Some(&50));
+
+        let mut empty = List::new();
+        let mut cursor = empty.indexer();
+        cursor.insert_after(1);
+        cursor.insert_before(2);
+        assert_eq!(empty.iter().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(empty.back(), Some(&2));
+    }
 }
@@ -1,45 +1,135 @@
 #![allow(dead_code)]
 
+use std::alloc::Layout;
 use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
 use std::ptr::NonNull;
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::hash::{Hash, Hasher};
+
+use super::binomial::BinomialHeap;
+use super::compare::{ByKey, Compare, FnCompare, KeyCompare, MaxOrder, MinOrder};
+use super::handle::{Brand, Identity, Slot, Ticket};
+use super::{AddressableHeap, Heap, HeapError, MeldableHeap, Recycled};
 // use std::collections::LinkedList;
 struct Node<T> {
     val: T,
     next: Option<NonNull<Node<T>>>,
+    /// Previous sibling, or the parent for the first child. `None` for the root.
+    prev: Option<NonNull<Node<T>>>,
     child: Option<NonNull<Node<T>>>,
+    /// Set only for nodes inserted with a handle.
+    slot: Option<Slot<Node<T>>>,
 }
 
-pub struct PairingHeap<T> {
+/// Node paired with its parent, which is `None` for the root.
+type Edge<T> = (Option<NonNull<Node<T>>>, NonNull<Node<T>>);
+
+pub struct PairingHeap<T, C = MinOrder> {
     head: Option<NonNull<Node<T>>>,
     size: usize,
+    cmp: C,
+    id: Identity,
     marker: PhantomData<Box<Node<T>>>,
 }
 
+/// Heap of `(key, value)` pairs ordered by the key alone, so `V` need not be comparable.
+///
+/// `delete_min` returns the whole pair. Create one with [`PairingHeap::new_map`] or
+/// `PairingHeap::with_compare(ByKey(cmp))`.
+pub type PairingMap<K, V, C = MinOrder> = PairingHeap<(K, V), ByKey<C>>;
+
+/// Points to an element inserted with [`PairingHeap::insert_with_handle`].
+///
+/// Its element never leaves its node, which is cut and re-melded as a whole. Uses are checked as
+/// described on [`AddressableHeap`].
+pub struct Handle<T>(Ticket<Node<T>>);
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.0).finish()
+    }
+}
+
+/// Guard returned by [`PairingHeap::peek_mut`].
+pub struct PeekMut<'a, T, C: Compare<T> = MinOrder> {
+    heap: &'a mut PairingHeap<T, C>,
+    modified: bool,
+}
+
 impl<T> Node<T> {
     const fn new(val: T) -> Self {
         Self {
             val,
             next: None,
+            prev: None,
             child: None,
+            slot: None,
         }
     }
 
     fn new_ptr(val: T) -> *mut Node<T> {
-        Box::leak(Box::new(Self {
-            val,
-            next: None,
-            child: None,
-        })) as *mut _
+        Box::leak(Box::new(Self::new(val))) as *mut _
+    }
+
+    /// Makes `child` the first child of `parent`.
+    fn link_child(parent: *mut Self, child: *mut Self) {
+        unsafe {
+            let child_nn = NonNull::new_unchecked(child);
+            if let Some(first) = (*parent).child {
+                (*first.as_ptr()).prev = Some(child_nn);
+            }
+            (*child).next = (*parent).child;
+            (*child).prev = NonNull::new(parent);
+            (*parent).child = Some(child_nn);
+        }
+    }
+
+    /// Detaches the subtree rooted at `node` from its parent and siblings. `node` mustn't be the root.
+    fn cut(node: *mut Self) {
+        unsafe {
+            let prev = (*node).prev.take().unwrap().as_ptr();
+            let next = (*node).next.take();
+            if (*prev).child.map(|p| p.as_ptr()) == Some(node) {
+                (*prev).child = next;
+            } else {
+                (*prev).next = next;
+            }
+            if let Some(next) = next {
+                (*next.as_ptr()).prev = NonNull::new(prev);
+            }
+        }
     }
 }
 
-impl<T: Ord> Node<T> {
+impl<T> Node<T> {
     /// root.next and other.next must be None.
     #[must_use]
-    fn merge(root: Option<NonNull<Self>>, other: Option<NonNull<Self>>) -> Option<NonNull<Self>> {
+    fn merge<C: Compare<T>>(
+        cmp: &C,
+        root: Option<NonNull<Self>>,
+        other: Option<NonNull<Self>>,
+    ) -> Option<NonNull<Self>> {
         match (root, other) {
             (Some(p1), Some(p2)) => unsafe {
-                Some((&*Self::merge_unchecked(p1.as_ptr(), p2.as_ptr())).into())
+                Some((&*Self::merge_unchecked(cmp, p1.as_ptr(), p2.as_ptr())).into())
             },
             (None, p) => p,
             (p, None) => p,
@@ -49,22 +139,23 @@ impl<T: Ord> Node<T> {
     /// root and other mustn't be null. \
     /// root.next and other.next must be None.
     #[must_use]
-    fn merge_unchecked(root: *mut Self, other: *mut Self) -> *mut Self {
+    fn merge_unchecked<C: Compare<T>>(cmp: &C, root: *mut Self, other: *mut Self) -> *mut Self {
         unsafe {
-            if (*root).val > (*other).val {
-                (*root).next = (*other).child;
-                (*other).child = Some((&*root).into());
+            if cmp.gt(&(*root).val, &(*other).val) {
+                Self::link_child(other, root);
                 other
             } else {
-                (*other).next = (*root).child;
-                (*root).child = Some((&*other).into());
+                Self::link_child(root, other);
                 root
             }
         }
     }
 
     #[must_use]
-    fn merge_list(mut root: Option<NonNull<Self>>) -> Option<NonNull<Self>> {
+    fn merge_list<C: Compare<T>>(
+        cmp: &C,
+        mut root: Option<NonNull<Self>>,
+    ) -> Option<NonNull<Self>> {
         let mut newtree = std::ptr::null_mut::<Self>();
         unsafe {
             while let Some(mut ptr) = root.map(|wrapped| wrapped.as_ptr()) {
@@ -73,9 +164,9 @@ impl<T: Ord> Node<T> {
                 if let Some(ptr2) = root.map(|wrapped| wrapped.as_ptr()) {
                     root = (*ptr2).next;
                     (*ptr2).next = None;
-                    ptr = Node::merge_unchecked(ptr, ptr2);
+                    ptr = Node::merge_unchecked(cmp, ptr, ptr2);
                 }
-                (*ptr).next = Some((&*newtree).into());
+                (*ptr).next = NonNull::new(newtree);
                 newtree = ptr;
             }
 
@@ -88,8 +179,9 @@ impl<T: Ord> Node<T> {
                 while let Some(ptr) = next.map(|wrapped| wrapped.as_ptr()) {
                     next = (*ptr).next;
                     (*ptr).next = None;
-                    newtree = Node::merge_unchecked(newtree, ptr);
+                    newtree = Node::merge_unchecked(cmp, newtree, ptr);
                 }
+                (*newtree).prev = None;
                 Some((&*newtree).into())
             }
         }
@@ -116,32 +208,196 @@ impl<T: fmt::Display> Node<T> {
 
 impl<T> PairingHeap<T> {
     pub const fn new() -> Self {
+        Self::with_compare(MinOrder)
+    }
+}
+
+impl<K: Ord, V> PairingHeap<(K, V), ByKey> {
+    /// Creates a key-value heap that serves the pair with the smallest key first.
+    pub const fn new_map() -> Self {
+        Self::with_compare(ByKey(MinOrder))
+    }
+}
+
+impl<T> PairingHeap<T, MaxOrder> {
+    /// Creates a heap that serves the largest element first.
+    pub const fn new_max() -> Self {
+        Self::with_compare(MaxOrder)
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> std::cmp::Ordering> PairingHeap<T, FnCompare<F>> {
+    /// Creates a heap ordered by `f`; the element that compares `Less` is served first.
+    pub const fn new_by(f: F) -> Self {
+        Self::with_compare(FnCompare(f))
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> PairingHeap<T, KeyCompare<F>> {
+    /// Creates a heap that serves the element with the smallest key `f(&val)` first.
+    pub const fn new_by_key(f: F) -> Self {
+        Self::with_compare(KeyCompare(f))
+    }
+}
+
+impl<T, C> PairingHeap<T, C> {
+    pub const fn with_compare(cmp: C) -> Self {
         Self {
             head: None,
             size: 0,
+            cmp,
+            id: Identity::new(),
             marker: PhantomData,
         }
     }
 
+    pub fn compare(&self) -> &C {
+        &self.cmp
+    }
+
     pub const fn len(&self) -> usize {
         self.size
     }
+
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
 }
 
-impl<T: Ord> PairingHeap<T> {
+impl<T, C: Default> Default for PairingHeap<T, C> {
+    fn default() -> Self {
+        Self::with_compare(C::default())
+    }
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
     pub fn insert(&mut self, val: T) {
         let node = Box::new(Node::new(val));
         let nodeptr = Box::leak(node).into();
-        self.head = Node::merge(self.head, Some(nodeptr));
+        self.head = Node::merge(&self.cmp, self.head, Some(nodeptr));
         self.size += 1;
     }
 
+    /// Inserts `val` and returns a handle to it for [`decrease_key`](Self::decrease_key).
+    pub fn insert_with_handle(&mut self, val: T) -> Handle<T> {
+        let node = unsafe { NonNull::new_unchecked(Node::new_ptr(val)) };
+        let (slot, ticket) = Ticket::issue(&mut self.id, node);
+        unsafe { (*node.as_ptr()).slot = Some(slot) };
+        self.head = Node::merge(&self.cmp, self.head, Some(node));
+        self.size += 1;
+        Handle(ticket)
+    }
+
+    /// Replaces the element of `handle` with `val`, which must not be greater.
+    ///
+    /// The subtree of the element is cut off and melded with the root, in amortized
+    /// sub-logarithmic time.
+    ///
+    /// # Panics
+    /// Panics if `val` is greater than the current element.
+    pub fn decrease_key(&mut self, handle: &Handle<T>, val: T) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.decrease_node(node, val) };
+        Ok(())
+    }
+
+    /// Removes the element of `handle` and returns it.
+    ///
+    /// The subtree of the element is cut off and its children are paired like in `delete_min`,
+    /// then melded with the root, in amortized O(log n).
+    pub fn remove(&mut self, handle: Handle<T>) -> Result<T, HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe {
+            self.detach_node(node);
+            Ok(Box::from_raw(node.as_ptr()).val)
+        }
+    }
+
+    /// Replaces the element of `handle` with `val`, which must not be less.
+    ///
+    /// The children of the element are paired like in `delete_min` and melded with the root,
+    /// then the element is melded back alone, in amortized O(log n).
+    ///
+    /// # Panics
+    /// Panics if `val` is less than the current element.
+    pub fn increase_key(&mut self, handle: &Handle<T>, val: T) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.increase_node(node, val) };
+        Ok(())
+    }
+
+    /// Replaces the element of `handle` with `f(element)`, calling
+    /// [`decrease_key`](Self::decrease_key) or [`increase_key`](Self::increase_key) depending on
+    /// the direction of the change.
+    pub fn update<F: FnOnce(&T) -> T>(
+        &mut self,
+        handle: &Handle<T>,
+        f: F,
+    ) -> Result<(), HeapError> {
+        let node = handle.0.node(&self.id)?;
+        unsafe { self.update_node(node, f) };
+        Ok(())
+    }
+
+    unsafe fn decrease_node(&mut self, nn: NonNull<Node<T>>, val: T) {
+        let node = nn.as_ptr();
+        assert!(
+            !self.cmp.gt(&val, &(*node).val),
+            "decrease_key: new value is greater than the current one"
+        );
+        (*node).val = val;
+        if self.head != Some(nn) {
+            Node::cut(node);
+            self.head = Node::merge(&self.cmp, self.head, Some(nn));
+        }
+    }
+
+    unsafe fn increase_node(&mut self, nn: NonNull<Node<T>>, val: T) {
+        let node = nn.as_ptr();
+        assert!(
+            !self.cmp.lt(&val, &(*node).val),
+            "increase_key: new value is less than the current one"
+        );
+        (*node).val = val;
+        if self.head == Some(nn) {
+            self.sift_head();
+        } else {
+            Node::cut(node);
+            let rest = Node::merge_list(&self.cmp, (*node).child.take());
+            self.head = Node::merge(&self.cmp, self.head, rest);
+            self.head = Node::merge(&self.cmp, self.head, Some(nn));
+        }
+    }
+
+    unsafe fn update_node<F: FnOnce(&T) -> T>(&mut self, nn: NonNull<Node<T>>, f: F) {
+        let node = nn.as_ptr();
+        let val = f(&(*node).val);
+        match self.cmp.compare(&val, &(*node).val) {
+            Ordering::Less => self.decrease_node(nn, val),
+            Ordering::Greater => self.increase_node(nn, val),
+            Ordering::Equal => (*node).val = val,
+        }
+    }
+
+    /// Unlinks `nn` from the heap without freeing it; its children are paired back into the root.
+    unsafe fn detach_node(&mut self, nn: NonNull<Node<T>>) {
+        let node = nn.as_ptr();
+        let rest = Node::merge_list(&self.cmp, (*node).child.take());
+        if self.head == Some(nn) {
+            self.head = rest;
+        } else {
+            Node::cut(node);
+            self.head = Node::merge(&self.cmp, self.head, rest);
+        }
+        self.size -= 1;
+    }
+
     pub fn delete_min(&mut self) -> Option<T> {
         if let Some(p) = self.head.map(|wrapped| wrapped.as_ptr()) {
             unsafe {
                 let tmp = (*p).child;
                 (*p).child = None;
-                self.head = Node::merge_list(tmp);
+                self.head = Node::merge_list(&self.cmp, tmp);
                 self.size -= 1;
                 Some(Box::from_raw(p).val)
             }
@@ -149,16 +405,885 @@ impl<T: Ord> PairingHeap<T> {
             None
         }
     }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.head.map(|p| unsafe { &(*p.as_ptr()).val })
+    }
+
+    /// Returns a guard that gives mutable access to the minimum.
+    ///
+    /// The heap order is restored when the guard is dropped.
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, C>> {
+        if self.head.is_some() {
+            Some(PeekMut {
+                heap: self,
+                modified: false,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Restores the heap order after the value of the root may have grown.
+    fn sift_head(&mut self) {
+        if let Some(p) = self.head.map(|wrapped| wrapped.as_ptr()) {
+            unsafe {
+                let children = (*p).child.take();
+                self.head =
+                    Node::merge(&self.cmp, Node::merge_list(&self.cmp, children), self.head);
+            }
+        }
+    }
+
+    /// Inserts `val` and then removes the minimum.
+    ///
+    /// Returns `val` right away if it is not greater than the current minimum.
+    pub fn push_pop(&mut self, val: T) -> T {
+        match self.peek() {
+            Some(min) if self.cmp.lt(min, &val) => self.replace_min(val).unwrap(),
+            _ => val,
+        }
+    }
+
+    /// Removes the minimum and inserts `val`, reusing the node of the removed element.
+    ///
+    /// Returns `None` and only inserts `val` if the heap is empty.
+    pub fn replace_min(&mut self, val: T) -> Option<T> {
+        if let Some(p) = self.head.map(|wrapped| wrapped.as_ptr()) {
+            let old = unsafe { std::mem::replace(&mut (*p).val, val) };
+            // handles of the old value must not reach the new one
+            unsafe { (*p).slot = None };
+            if unsafe { self.cmp.gt(&(*p).val, &old) } {
+                self.sift_head();
+            }
+            Some(old)
+        } else {
+            self.insert(val);
+            None
+        }
+    }
+
+    /// Moves all elements of `other` into `self` in O(1).
+    pub fn meld(&mut self, mut other: Self) {
+        self.append(&mut other);
+    }
+
+    /// Moves all elements of `other` into `self` in O(1), leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        self.head = Node::merge(&self.cmp, self.head, other.head.take());
+        self.size += std::mem::take(&mut other.size);
+        self.id.absorb(&mut other.id);
+    }
+
+    /// Melds every heap yielded by `heaps` into one, ordered by `C::default()`.
+    ///
+    /// The roots are combined with the same two-pass pairing as `delete_min`, in time linear in the number of heaps.
+    pub fn meld_all<I: IntoIterator<Item = Self>>(heaps: I) -> Self
+    where
+        C: Default,
+    {
+        let cmp = C::default();
+        let mut roots = None;
+        let mut size = 0;
+        let mut id = Identity::new();
+        for mut heap in heaps {
+            if let Some(head) = heap.head.take() {
+                unsafe {
+                    (*head.as_ptr()).next = roots;
+                }
+                roots = Some(head);
+                size += std::mem::take(&mut heap.size);
+            }
+            id.absorb(&mut heap.id);
+        }
+        Self {
+            head: Node::merge_list(&cmp, roots),
+            size,
+            cmp,
+            id,
+            marker: PhantomData,
+        }
+    }
+
+    /// Keeps only the elements for which `pred` returns `true`.
+    ///
+    /// Every node is visited once and the survivors are linked again with two-pass pairing, in
+    /// O(n). Handles to the removed elements become stale.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, pred: F) {
+        let (rejected, len) = self.sift(pred);
+        drop(IntoIter {
+            head: rejected,
+            len,
+            marker: PhantomData,
+        });
+    }
+
+    /// Splits the heap into the elements for which `pred` returns `true` and the others, in O(n)
+    /// like [`retain`](Self::retain).
+    ///
+    /// Handles keep working on the first heap; handles to the elements of the second one become
+    /// stale.
+    pub fn partition<F: FnMut(&T) -> bool>(mut self, pred: F) -> (Self, Self)
+    where
+        C: Clone,
+    {
+        let (rejected, size) = self.sift(pred);
+        let mut p = rejected;
+        while let Some(node) = p {
+            unsafe {
+                (*node.as_ptr()).slot = None;
+                p = (*node.as_ptr()).next;
+            }
+        }
+        let other = Self {
+            head: Node::merge_list(&self.cmp, rejected),
+            size,
+            cmp: self.cmp.clone(),
+            id: Identity::new(),
+            marker: PhantomData,
+        };
+        (self, other)
+    }
+
+    /// Removes the elements for which `pred` returns `true` and returns them in heap order.
+    ///
+    /// `pred` must hold for a prefix of the heap order, like `|&t| t <= now`. Subtrees are pruned
+    /// at the first element that fails it, so only the removed elements and their children are
+    /// visited, in amortized O(k log n) for k removed elements.
+    pub fn pop_while<F: FnMut(&T) -> bool>(&mut self, pred: F) -> Vec<T> {
+        let mut vals: Vec<T> = self
+            .take_while_nodes(pred)
+            .into_iter()
+            .map(|(_, node)| unsafe { Box::from_raw(node.as_ptr()).val })
+            .collect();
+        vals.sort_by(|a, b| self.cmp.compare(a, b));
+        vals
+    }
+
+    /// Moves the elements less than `bound` into a new heap, pruning subtrees like
+    /// [`pop_while`](Self::pop_while).
+    ///
+    /// The moved elements keep their tree shape, so no comparison is needed to build the new heap.
+    /// Handles to them become stale.
+    pub fn split_off_below(&mut self, bound: &T) -> Self
+    where
+        C: Clone,
+    {
+        let cmp = self.cmp.clone();
+        let nodes = self.take_while_nodes(|val| cmp.lt(val, bound));
+        let mut other = Self::with_compare(cmp);
+        other.size = nodes.len();
+        for &(parent, node) in nodes.iter().rev() {
+            match parent {
+                Some(parent) => Node::link_child(parent.as_ptr(), node.as_ptr()),
+                None => other.head = Some(node),
+            }
+        }
+        other
+    }
+
+    /// Detaches the elements reached from the root through elements satisfying `pred`, with
+    /// their slots dropped, and returns them paired with their parent, parents first.
+    ///
+    /// The children left behind are paired back into the heap. The links of the returned nodes
+    /// are left unspecified, except that they have no children. `head` and `size` are only
+    /// written once `pred` has seen every node it will see.
+    fn take_while_nodes<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> Vec<Edge<T>> {
+        let mut taken = Vec::new();
+        let mut orphans = Vec::new();
+        let mut stack: Vec<_> = self
+            .head
+            .filter(|head| pred(unsafe { &(*head.as_ptr()).val }))
+            .map(|head| (None, head))
+            .into_iter()
+            .collect();
+        while let Some((parent, node)) = stack.pop() {
+            taken.push((parent, node));
+            let mut child = unsafe { (*node.as_ptr()).child };
+            while let Some(ch) = child {
+                unsafe {
+                    child = (*ch.as_ptr()).next;
+                    if pred(&(*ch.as_ptr()).val) {
+                        stack.push((Some(node), ch));
+                    } else {
+                        orphans.push(ch);
+                    }
+                }
+            }
+        }
+        if taken.is_empty() {
+            return taken;
+        }
+        let mut rest = None;
+        for ch in orphans {
+            unsafe { (*ch.as_ptr()).next = rest };
+            rest = Some(ch);
+        }
+        for &(_, node) in &taken {
+            unsafe {
+                (*node.as_ptr()).child = None;
+                (*node.as_ptr()).slot = None;
+            }
+        }
+        self.head = Node::merge_list(&self.cmp, rest);
+        self.size -= taken.len();
+        taken
+    }
+
+    /// Unlinks the elements for which `pred` returns `false` and returns them as a chain, with
+    /// their number. The others are paired back into the heap.
+    fn sift<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> (Option<NonNull<Node<T>>>, usize) {
+        let mut sieve = Sieve {
+            rest: self.head.take(),
+            kept: None,
+            rejected: None,
+            heap: self,
+        };
+        let mut count = 0;
+        while let Some(node) = Node::pop_leaf(&mut sieve.rest) {
+            unsafe { (*node.as_ptr()).next = sieve.kept };
+            sieve.kept = Some(node);
+            if !pred(unsafe { &(*node.as_ptr()).val }) {
+                unsafe {
+                    sieve.kept = (*node.as_ptr()).next;
+                    (*node.as_ptr()).next = sieve.rejected;
+                }
+                sieve.rejected = Some(node);
+                count += 1;
+            }
+        }
+        sieve.heap.size -= count;
+        (sieve.rejected.take(), count)
+    }
+}
+
+/// Nodes of a heap being sifted by `retain` or `partition`, linked back into the heap on drop.
+///
+/// While sifting, `heap.head` is `None` and every node is in one of `rest`, `kept` and
+/// `rejected`. A node is pushed onto `kept` before the predicate sees it and moved to `rejected`
+/// after. If the predicate panics, the drop pairs all three lists back into `heap.head`, whose
+/// `size` has not been lowered yet.
+struct Sieve<'a, T, C: Compare<T>> {
+    heap: &'a mut PairingHeap<T, C>,
+    /// Trees not visited yet.
+    rest: Option<NonNull<Node<T>>>,
+    kept: Option<NonNull<Node<T>>>,
+    rejected: Option<NonNull<Node<T>>>,
+}
+
+impl<T, C: Compare<T>> Drop for Sieve<'_, T, C> {
+    fn drop(&mut self) {
+        while let Some(node) =
+            Node::pop_leaf(&mut self.rest).or_else(|| Node::pop_leaf(&mut self.rejected))
+        {
+            unsafe { (*node.as_ptr()).next = self.kept };
+            self.kept = Some(node);
+        }
+        self.heap.head = Node::merge_list(&self.heap.cmp, self.kept.take());
+    }
+}
+
+impl<T, C: Compare<T>> Heap for PairingHeap<T, C> {
+    type Item = T;
+
+    fn insert(&mut self, item: T) {
+        Self::insert(self, item);
+    }
+
+    fn delete_min(&mut self) -> Option<T> {
+        Self::delete_min(self)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        Self::peek(self)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+impl<T, C: Compare<T>> AddressableHeap for PairingHeap<T, C> {
+    type Handle = Handle<T>;
+
+    fn insert_with_handle(&mut self, item: T) -> Handle<T> {
+        Self::insert_with_handle(self, item)
+    }
+
+    fn decrease_key(&mut self, handle: &Handle<T>, item: T) -> Result<(), HeapError> {
+        Self::decrease_key(self, handle, item)
+    }
+
+    fn remove(&mut self, handle: Handle<T>) -> Result<T, HeapError> {
+        Self::remove(self, handle)
+    }
+
+    fn increase_key(&mut self, handle: &Handle<T>, item: T) -> Result<(), HeapError> {
+        Self::increase_key(self, handle, item)
+    }
+
+    fn update<F: FnOnce(&T) -> T>(&mut self, handle: &Handle<T>, f: F) -> Result<(), HeapError> {
+        Self::update(self, handle, f)
+    }
+}
+
+impl<T, C: Compare<T>> MeldableHeap for PairingHeap<T, C> {
+    fn meld(&mut self, other: Self) {
+        Self::meld(self, other);
+    }
+}
+
+/// Handle to an element inserted through a [`Scope`], usable only within that scope.
+pub struct BrandedHandle<'id, T> {
+    node: NonNull<Node<T>>,
+    brand: Brand<'id>,
+}
+
+impl<T> Clone for BrandedHandle<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BrandedHandle<'_, T> {}
+
+impl<T> PartialEq for BrandedHandle<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T> Eq for BrandedHandle<'_, T> {}
+
+impl<T> fmt::Debug for BrandedHandle<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BrandedHandle").field(&self.node).finish()
+    }
+}
+
+/// View of a [`PairingHeap`] whose handles are checked at compile time, created by
+/// [`PairingHeap::scope`].
+pub struct Scope<'id, 'a, T, C = MinOrder> {
+    heap: &'a mut PairingHeap<T, C>,
+    /// Nodes removed in this scope, chained by `next` and freed when the scope ends.
+    retired: Option<NonNull<Node<T>>>,
+    brand: Brand<'id>,
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    /// Calls `f` with a [`Scope`] whose handles are raw node pointers branded with a lifetime
+    /// unique to this call.
+    ///
+    /// The handles cannot leave `f` or be passed to another scope, so unlike [`Handle`] they carry
+    /// no heap id. Nodes removed inside the scope stay allocated until it ends and are marked in
+    /// place, so a handle to a removed element still fails with [`HeapError::StaleHandle`].
+    ///
+    /// ```compile_fail
+    /// # use heaps::heap::pairing::PairingHeap;
+    /// let (mut a, mut b) = (PairingHeap::<i32>::new(), PairingHeap::<i32>::new());
+    /// a.scope(|mut sa| {
+    ///     let h = sa.insert_with_handle(1);
+    ///     b.scope(|mut sb| sb.decrease_key(h, 0).ok());
+    /// });
+    /// ```
+    pub fn scope<'a, R, F>(&'a mut self, f: F) -> R
+    where
+        F: for<'id> FnOnce(Scope<'id, 'a, T, C>) -> R,
+    {
+        f(Scope {
+            heap: self,
+            retired: None,
+            brand: PhantomData,
+        })
+    }
+}
+
+impl<'id, T, C: Compare<T>> Scope<'id, '_, T, C> {
+    pub fn insert(&mut self, val: T) {
+        self.heap.insert(val);
+    }
+
+    pub fn insert_with_handle(&mut self, val: T) -> BrandedHandle<'id, T> {
+        let node = unsafe { NonNull::new_unchecked(Node::new_ptr(val)) };
+        self.heap.head = Node::merge(&self.heap.cmp, self.heap.head, Some(node));
+        self.heap.size += 1;
+        BrandedHandle {
+            node,
+            brand: PhantomData,
+        }
+    }
+
+    /// Same as [`PairingHeap::decrease_key`].
+    pub fn decrease_key(&mut self, handle: BrandedHandle<'id, T>, val: T) -> Result<(), HeapError> {
+        let node = self.node(handle)?;
+        unsafe { self.heap.decrease_node(node, val) };
+        Ok(())
+    }
+
+    /// Same as [`PairingHeap::increase_key`].
+    pub fn increase_key(&mut self, handle: BrandedHandle<'id, T>, val: T) -> Result<(), HeapError> {
+        let node = self.node(handle)?;
+        unsafe { self.heap.increase_node(node, val) };
+        Ok(())
+    }
+
+    /// Same as [`PairingHeap::update`].
+    pub fn update<F: FnOnce(&T) -> T>(
+        &mut self,
+        handle: BrandedHandle<'id, T>,
+        f: F,
+    ) -> Result<(), HeapError> {
+        let node = self.node(handle)?;
+        unsafe { self.heap.update_node(node, f) };
+        Ok(())
+    }
+
+    /// Same as [`PairingHeap::remove`].
+    pub fn remove(&mut self, handle: BrandedHandle<'id, T>) -> Result<T, HeapError> {
+        let node = self.node(handle)?;
+        unsafe {
+            self.heap.detach_node(node);
+            Ok(self.retire(node))
+        }
+    }
+
+    pub fn delete_min(&mut self) -> Option<T> {
+        let node = self.heap.head?;
+        unsafe {
+            self.heap.detach_node(node);
+            Some(self.retire(node))
+        }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.peek()
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// A removed node points back to itself.
+    fn node(&self, handle: BrandedHandle<'id, T>) -> Result<NonNull<Node<T>>, HeapError> {
+        if unsafe { (*handle.node.as_ptr()).prev } == Some(handle.node) {
+            Err(HeapError::StaleHandle)
+        } else {
+            Ok(handle.node)
+        }
+    }
+
+    /// Moves the element out of the detached node `nn` and keeps the node until the scope ends.
+    unsafe fn retire(&mut self, nn: NonNull<Node<T>>) -> T {
+        let node = nn.as_ptr();
+        (*node).slot = None;
+        (*node).prev = Some(nn);
+        (*node).next = self.retired;
+        self.retired = Some(nn);
+        std::ptr::read(&(*node).val)
+    }
+}
+
+impl<T, C> Drop for Scope<'_, '_, T, C> {
+    fn drop(&mut self) {
+        while let Some(nn) = self.retired {
+            unsafe {
+                self.retired = (*nn.as_ptr()).next;
+                // the element was moved out, so only the memory is released
+                std::alloc::dealloc(nn.as_ptr() as *mut u8, Layout::new::<Node<T>>());
+            }
+        }
+    }
 }
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    /// Returns the elements in the order `delete_min` would return them.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.into_iter_sorted().collect()
+    }
+
+    /// Returns an iterator that yields the elements in heap order by repeated `delete_min`.
+    pub fn into_iter_sorted(self) -> IntoIterSorted<T, C> {
+        IntoIterSorted { heap: self }
+    }
+
+    /// Removes the elements in heap order.
+    ///
+    /// Elements not yielded are dropped together with the iterator.
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T, C> {
+        DrainSorted { heap: self }
+    }
+
+    /// Returns an iterator that yields the elements in heap order without modifying the heap.
+    ///
+    /// A frontier of candidates starts with the root; each yielded element adds its children.
+    /// The first k elements take O(k log k) comparisons when nodes have few children, and in
+    /// general time linear in the children of the yielded elements, times the log of the frontier
+    /// size.
+    pub fn iter_sorted(&self) -> IterSorted<'_, T, C> {
+        let frontier = self
+            .head
+            .map(|head| Candidate {
+                node: unsafe { &*head.as_ptr() },
+                cmp: &self.cmp,
+            })
+            .into_iter()
+            .collect();
+        IterSorted {
+            frontier,
+            len: self.size,
+        }
+    }
+
+    /// Returns the `k` first elements in heap order, or all of them if there are fewer.
+    pub fn k_smallest(&self, k: usize) -> Vec<&T> {
+        self.iter_sorted().take(k).collect()
+    }
+}
+
+/// Node of the frontier of [`IterSorted`], ordered so that `BinaryHeap` pops the first one.
+struct Candidate<'a, T, C> {
+    node: &'a Node<T>,
+    cmp: &'a C,
+}
+
+impl<T, C> Clone for Candidate<'_, T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            cmp: self.cmp,
+        }
+    }
+}
+
+impl<T, C: Compare<T>> PartialEq for Candidate<'_, T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, C: Compare<T>> Eq for Candidate<'_, T, C> {}
+
+impl<T, C: Compare<T>> PartialOrd for Candidate<'_, T, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, C: Compare<T>> Ord for Candidate<'_, T, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp.compare(&other.node.val, &self.node.val)
+    }
+}
+
+/// Iterator returned by [`PairingHeap::iter_sorted`].
+pub struct IterSorted<'a, T, C = MinOrder> {
+    frontier: BinaryHeap<Candidate<'a, T, C>>,
+    len: usize,
+}
+
+impl<'a, T, C: Compare<T>> Iterator for IterSorted<'a, T, C> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let Candidate { node, cmp } = self.frontier.pop()?;
+        let mut child = node.child;
+        while let Some(ch) = child {
+            let ch = unsafe { &*ch.as_ptr() };
+            self.frontier.push(Candidate { node: ch, cmp });
+            child = ch.next;
+        }
+        self.len -= 1;
+        Some(&node.val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, C: Compare<T>> ExactSizeIterator for IterSorted<'_, T, C> {}
+
+impl<T, C> Clone for IterSorted<'_, T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            frontier: self.frontier.clone(),
+            len: self.len,
+        }
+    }
+}
+
+/// Iterator returned by [`PairingHeap::into_iter_sorted`].
+pub struct IntoIterSorted<T, C = MinOrder> {
+    heap: PairingHeap<T, C>,
+}
+
+impl<T, C: Compare<T>> Iterator for IntoIterSorted<T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.heap.delete_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+impl<T, C: Compare<T>> ExactSizeIterator for IntoIterSorted<T, C> {}
+
+/// Iterator returned by [`PairingHeap::drain_sorted`].
+pub struct DrainSorted<'a, T, C = MinOrder> {
+    heap: &'a mut PairingHeap<T, C>,
+}
+
+impl<T, C: Compare<T>> Iterator for DrainSorted<'_, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.heap.delete_min()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+impl<T, C: Compare<T>> ExactSizeIterator for DrainSorted<'_, T, C> {}
+
+impl<T, C> Drop for DrainSorted<'_, T, C> {
+    fn drop(&mut self) {
+        self.heap.clear();
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for PairingHeap<T, C> {
+    /// Links the elements with two-pass pairing, using O(n) comparisons.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = Self::default();
+        heap.extend(iter);
+        heap
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for PairingHeap<T, C> {
+    /// Links the new elements among themselves with two-pass pairing before melding them in.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut batch = Batch {
+            heap: self,
+            roots: None,
+        };
+        for val in iter {
+            let node = Node::new_ptr(val);
+            unsafe {
+                (*node).next = batch.roots;
+                batch.roots = Some(NonNull::new_unchecked(node));
+            }
+            batch.heap.size += 1;
+        }
+    }
+}
+
+/// New nodes of `extend`, melded into the heap on drop, so none is lost if the iterator panics.
+struct Batch<'a, T, C: Compare<T>> {
+    heap: &'a mut PairingHeap<T, C>,
+    roots: Option<NonNull<Node<T>>>,
+}
+
+impl<T, C: Compare<T>> Drop for Batch<'_, T, C> {
+    fn drop(&mut self) {
+        let batch = Node::merge_list(&self.heap.cmp, self.roots.take());
+        self.heap.head = Node::merge(&self.heap.cmp, self.heap.head, batch);
+    }
+}
+
+impl<T: Ord> From<Vec<T>> for PairingHeap<T> {
+    fn from(vec: Vec<T>) -> Self {
+        vec.into_iter().collect()
+    }
+}
+
+impl<T: Ord, const N: usize> From<[T; N]> for PairingHeap<T> {
+    fn from(arr: [T; N]) -> Self {
+        arr.into_iter().collect()
+    }
+}
+
+/// Builds the pairing nodes in the memory of the binomial ones, which are larger, and pairs
+/// them like in `delete_min`, in O(n).
+impl<T, C: Compare<T>> From<BinomialHeap<T, C>> for PairingHeap<T, C> {
+    fn from(heap: BinomialHeap<T, C>) -> Self {
+        let size = heap.len();
+        let mut head = None;
+        let cmp = heap.into_recycled(|val, memory| {
+            let node = memory.reuse(Node::new(val));
+            unsafe { (*node.as_ptr()).next = head };
+            head = Some(node);
+        });
+        Self {
+            head: Node::merge_list(&cmp, head),
+            size,
+            cmp,
+            id: Identity::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    /// Orders `vals` bottom-up into an implicit binary heap, where `i` is the parent of `2i + 1`
+    /// and `2i + 2`, and links that tree as a pairing tree, in O(n).
+    fn from_heapified(mut vals: Vec<T>, cmp: C) -> Self {
+        let n = vals.len();
+        for i in (0..n / 2).rev() {
+            let mut p = i;
+            while 2 * p + 1 < n {
+                let l = 2 * p + 1;
+                let c = if l + 1 < n && cmp.lt(&vals[l + 1], &vals[l]) {
+                    l + 1
+                } else {
+                    l
+                };
+                if !cmp.lt(&vals[c], &vals[p]) {
+                    break;
+                }
+                vals.swap(c, p);
+                p = c;
+            }
+        }
+        let nodes: Vec<_> = vals
+            .into_iter()
+            .map(|val| unsafe { NonNull::new_unchecked(Node::new_ptr(val)) })
+            .collect();
+        for i in (1..nodes.len()).rev() {
+            Node::link_child(nodes[(i - 1) / 2].as_ptr(), nodes[i].as_ptr());
+        }
+        Self {
+            head: nodes.first().copied(),
+            size: nodes.len(),
+            cmp,
+            id: Identity::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, C> PairingHeap<T, C> {
+    /// Consumes the heap and passes every element to `f` in arbitrary order, together with the
+    /// memory of its node.
+    pub(super) fn into_recycled<F: FnMut(T, Recycled)>(self, mut f: F) -> C {
+        let mut this = ManuallyDrop::new(self);
+        let cmp = unsafe { std::ptr::read(&this.cmp) };
+        drop(std::mem::replace(&mut this.id, Identity::new()));
+        let mut head = this.head.take();
+        while let Some(node) = Node::pop_leaf(&mut head) {
+            let Node { val, slot, .. } = unsafe { std::ptr::read(node.as_ptr()) };
+            drop(slot);
+            f(val, unsafe { Recycled::new(node) });
+        }
+        cmp
+    }
+}
+
+/// Heapifies the elements of `heap` bottom-up and links the implicit tree as a pairing tree, in
+/// O(n). The order of [`BinaryHeap::into_vec`] is not relied upon.
+impl<T: Ord> From<BinaryHeap<Reverse<T>>> for PairingHeap<T> {
+    fn from(heap: BinaryHeap<Reverse<T>>) -> Self {
+        let vals = heap
+            .into_vec()
+            .into_iter()
+            .map(|Reverse(val)| val)
+            .collect();
+        Self::from_heapified(vals, MinOrder)
+    }
+}
+
+/// Heapifies the elements of `heap` bottom-up and links the implicit tree as a pairing tree, in
+/// O(n). The order of [`BinaryHeap::into_vec`] is not relied upon.
+impl<T: Ord> From<BinaryHeap<T>> for PairingHeap<T, MaxOrder> {
+    fn from(heap: BinaryHeap<T>) -> Self {
+        Self::from_heapified(heap.into_vec(), MaxOrder)
+    }
+}
+
+/// Heapifies the elements in O(n).
+impl<T: Ord, C> From<PairingHeap<T, C>> for BinaryHeap<T> {
+    fn from(heap: PairingHeap<T, C>) -> Self {
+        BinaryHeap::from(heap.into_iter().collect::<Vec<_>>())
+    }
+}
+
+/// Heapifies the elements in O(n), keeping the minimum on top.
+impl<T: Ord, C> From<PairingHeap<T, C>> for BinaryHeap<Reverse<T>> {
+    fn from(heap: PairingHeap<T, C>) -> Self {
+        BinaryHeap::from(heap.into_iter().map(Reverse).collect::<Vec<_>>())
+    }
+}
+
+impl<T, C: Compare<T>> PeekMut<'_, T, C> {
+    /// Removes the peeked element from the heap.
+    pub fn pop(mut this: Self) -> T {
+        this.modified = false;
+        this.heap.delete_min().unwrap()
+    }
+}
+
+impl<T, C: Compare<T>> Deref for PeekMut<'_, T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.heap.peek().unwrap()
+    }
+}
+
+impl<T, C: Compare<T>> DerefMut for PeekMut<'_, T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.modified = true;
+        unsafe { &mut (*self.heap.head.unwrap().as_ptr()).val }
+    }
+}
+
+impl<T, C: Compare<T>> Drop for PeekMut<'_, T, C> {
+    fn drop(&mut self) {
+        if self.modified {
+            self.heap.sift_head();
+        }
+    }
+}
+
 use std::fmt;
 
-impl<T: fmt::Display> fmt::Debug for PairingHeap<T> {
+impl<T: fmt::Display, C> fmt::Debug for PairingHeap<T, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Node::fmt_rec(self.head, 0, f)
     }
 }
 
-impl<T: Clone> Clone for PairingHeap<T> {
+/// Compares the elements as multisets, whatever the shape of the trees.
+///
+/// Both heaps are walked in heap order, in O(n log n). Elements that the comparator of `self`
+/// considers equivalent are matched with `==` in any order, which costs O(r²) comparisons for
+/// each run of r equivalent elements, so O(n²) if all of them are equivalent.
+impl<T: PartialEq, C: Compare<T>> PartialEq for PairingHeap<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && super::sorted_multiset_eq(self.iter_sorted(), other.iter_sorted(), &self.cmp)
+    }
+}
+
+impl<T: Eq, C: Compare<T>> Eq for PairingHeap<T, C> {}
+
+/// Hashes the elements in an order-independent way, consistent with `PartialEq`.
+impl<T: Hash, C> Hash for PairingHeap<T, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        super::hash_unordered(self.iter(), state);
+    }
+}
+
+impl<T: Clone, C: Clone> Clone for PairingHeap<T, C> {
     fn clone(&self) -> Self {
         let new_head = self.head.map(|nn| {
             let mut stack = Vec::with_capacity(self.size);
@@ -170,11 +1295,13 @@ impl<T: Clone> Clone for PairingHeap<T> {
                         if let Some(ne) = (*old).next {
                             let new_node_ptr = Node::new_ptr((*ne.as_ptr()).val.clone());
                             (*new).next = Some(NonNull::new_unchecked(new_node_ptr));
+                            (*new_node_ptr).prev = NonNull::new(new);
                             stack.push((ne.as_ptr(), new_node_ptr));
                         }
                         if let Some(ch) = (*old).child {
                             let new_node_ptr = Node::new_ptr((*ch.as_ptr()).val.clone());
                             (*new).child = Some(NonNull::new_unchecked(new_node_ptr));
+                            (*new_node_ptr).prev = NonNull::new(new);
                             old = ch.as_ptr();
                             new = new_node_ptr;
                         } else {
@@ -188,36 +1315,218 @@ impl<T: Clone> Clone for PairingHeap<T> {
         Self {
             head: new_head,
             size: self.size,
+            cmp: self.cmp.clone(),
+            id: Identity::new(),
             marker: PhantomData,
         }
     }
 }
 
-impl<T> Drop for PairingHeap<T> {
-    fn drop(&mut self) {
-        if let Some(head) = self.head.map(|nn| nn.as_ptr()) {
-            let mut stack = Vec::with_capacity(self.size);
-            stack.push(head);
-            while let Some(mut ptr) = stack.pop() {
-                unsafe {
-                    loop {
-                        if let Some(ne) = (*ptr).next {
-                            stack.push(ne.as_ptr());
-                        }
-                        if let Some(ch) = (*ptr).child {
-                            std::ptr::drop_in_place(ptr);
-                            ptr = ch.as_ptr();
-                        } else {
-                            std::ptr::drop_in_place(ptr);
-                            break;
+impl<T> Node<T> {
+    /// Detaches a node without children from the front of the sibling chain `head`.
+    ///
+    /// Children are rotated into the chain on the way, so draining a whole tree needs no extra space.
+    fn pop_leaf(head: &mut Option<NonNull<Self>>) -> Option<NonNull<Self>> {
+        let mut top = (*head)?;
+        unsafe {
+            while let Some(ch) = (*top.as_ptr()).child {
+                (*top.as_ptr()).child = (*ch.as_ptr()).next;
+                (*ch.as_ptr()).next = Some(top);
+                top = ch;
+            }
+            *head = (*top.as_ptr()).next.take();
+        }
+        Some(top)
+    }
+}
+
+impl<T, C> PairingHeap<T, C> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            stack: self.head.into_iter().collect(),
+            len: self.size,
+            marker: PhantomData,
+        }
+    }
+
+    /// Removes all elements in arbitrary order.
+    ///
+    /// The heap is emptied immediately, even if the iterator is not consumed.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain {
+            iter: IntoIter {
+                head: self.head.take(),
+                len: std::mem::take(&mut self.size),
+                marker: PhantomData,
+            },
+            marker: PhantomData,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.drain();
+    }
+
+    /// Rewrites every element in place with `f`, without relinking or allocating, in O(n).
+    ///
+    /// `f` must be monotone in the heap order: if `a` comes before `b`, `f(a)` must not come
+    /// after `f(b)`. The heap order is then preserved, and handles keep pointing to their
+    /// elements. Otherwise, or if `f` panics halfway, the order of later operations is
+    /// unspecified, but the heap stays safe to use and drop.
+    ///
+    /// Shifting every element by the same delta is done with this method too. There is no lazy
+    /// offset kept at the root, because `peek`, the iterators and the handles lend out references
+    /// to the stored elements, which would have to include it.
+    pub fn map_keys_monotone<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        let mut cur = self.head;
+        while let Some(node) = cur {
+            unsafe {
+                f(&mut (*node.as_ptr()).val);
+                if let Some(child) = (*node.as_ptr()).child {
+                    cur = Some(child);
+                    continue;
+                }
+                // move on to the next sibling of the nearest ancestor that has one
+                let mut p = node;
+                cur = loop {
+                    if let Some(next) = (*p.as_ptr()).next {
+                        break Some(next);
+                    }
+                    // the parent is the first node back whose first child is the last one seen
+                    let mut first = p;
+                    let parent = loop {
+                        match (*first.as_ptr()).prev {
+                            Some(prev) if (*prev.as_ptr()).child == Some(first) => {
+                                break Some(prev)
+                            }
+                            Some(prev) => first = prev,
+                            None => break None,
                         }
+                    };
+                    match parent {
+                        Some(parent) => p = parent,
+                        None => break None,
                     }
-                }
+                };
             }
         }
     }
 }
 
+/// Iterator over the elements of a [`PairingHeap`] in arbitrary order.
+pub struct Iter<'a, T> {
+    stack: Vec<NonNull<Node<T>>>,
+    len: usize,
+    marker: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.stack.pop()?;
+        unsafe {
+            self.stack.extend((*node.as_ptr()).next);
+            self.stack.extend((*node.as_ptr()).child);
+            self.len -= 1;
+            Some(&(*node.as_ptr()).val)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Owning iterator over the elements of a [`PairingHeap`] in arbitrary order.
+pub struct IntoIter<T> {
+    head: Option<NonNull<Node<T>>>,
+    len: usize,
+    marker: PhantomData<Box<Node<T>>>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = Node::pop_leaf(&mut self.head)?;
+        self.len -= 1;
+        unsafe { Some(Box::from_raw(node.as_ptr()).val) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+/// Draining iterator returned by [`PairingHeap::drain`].
+pub struct Drain<'a, T> {
+    iter: IntoIter<T>,
+    marker: PhantomData<&'a mut Node<T>>,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T, C> IntoIterator for PairingHeap<T, C> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> IntoIter<T> {
+        IntoIter {
+            head: self.head.take(),
+            len: std::mem::take(&mut self.size),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, C> IntoIterator for &'a PairingHeap<T, C> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T, C> Drop for PairingHeap<T, C> {
+    fn drop(&mut self) {
+        self.drain();
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -245,4 +1554,90 @@ mod test {
         println!("old: \n{:?}", &heap);
         println!("cloned: \n{:?}", heap.clone());
     }
+
+    #[test]
+    fn meld_test() {
+        let mut a = PairingHeap::new();
+        let mut b = PairingHeap::new();
+        for i in 0..20 {
+            if i % 3 == 0 {
+                a.insert(i);
+            } else {
+                b.insert(i);
+            }
+        }
+        a.append(&mut b);
+        assert_eq!(a.len(), 20);
+        assert!(b.is_empty());
+        let heaps = (0..5).map(|i| {
+            let mut h = PairingHeap::new();
+            h.insert(100 - i);
+            h.insert(200 + i);
+            h
+        });
+        a.meld(PairingHeap::meld_all(heaps));
+        assert_eq!(a.len(), 30);
+        let v: Vec<i32> = (0..a.len()).map(|_| a.delete_min().unwrap()).collect();
+        let mut expected: Vec<i32> = (0..20).chain(96..=100).chain(200..205).collect();
+        expected.sort();
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn convert_test() {
+        let min: BinaryHeap<Reverse<i32>> = (0..40).rev().map(Reverse).collect();
+        let mut heap = PairingHeap::<i32>::from(min);
+        assert_eq!(heap.delete_min(), Some(0));
+        let max: BinaryHeap<i32> = heap.clone().into();
+        assert_eq!(max.peek(), Some(&39));
+        let min: BinaryHeap<Reverse<i32>> = heap.into();
+        assert_eq!(min.peek(), Some(&Reverse(1)));
+        assert_eq!(
+            PairingHeap::from(max).into_sorted_vec(),
+            (1..40).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            PairingHeap::<i32>::from(min).into_sorted_vec(),
+            (1..40).collect::<Vec<_>>()
+        );
+
+        let mut binomial = BinomialHeap::new();
+        let handles: Vec<_> = (0..50)
+            .map(|i| binomial.insert_with_handle((i * 7 % 50).to_string()))
+            .collect();
+        binomial.delete_min();
+        let mut expected: Vec<_> = binomial.iter().cloned().collect();
+        expected.sort();
+        let mut heap = PairingHeap::from(binomial);
+        assert_eq!(heap.len(), 49);
+        heap.insert("-".to_string());
+        assert_eq!(heap.delete_min().as_deref(), Some("-"));
+        assert_eq!(heap.into_sorted_vec(), expected);
+        drop(handles);
+    }
+
+    #[test]
+    fn decrease_key_test() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut heap = PairingHeap::new();
+        // distinct values, so popped elements can be told apart by value
+        let mut live: Vec<_> = (0..300)
+            .map(|i| i * 37 % 300 * 10)
+            .map(|v| (heap.insert_with_handle(v), v))
+            .collect();
+        for _ in 0..20 {
+            let min = heap.delete_min().unwrap();
+            live.retain(|&(_, v)| v != min);
+        }
+        for (h, v) in live.iter_mut().take(100) {
+            *v -= rng.gen_range(0..20000);
+            heap.decrease_key(h, *v).unwrap();
+        }
+        let mut vals: Vec<i32> = live.iter().map(|&(_, v)| v).collect();
+        vals.sort();
+        assert_eq!(heap.clone().into_sorted_vec(), vals);
+        assert_eq!(heap.into_sorted_vec(), vals);
+    }
 }
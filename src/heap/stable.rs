@@ -0,0 +1,195 @@
+use std::cmp::Ordering;
+use std::sync::atomic::{self, AtomicU64};
+
+use super::binomial::BinomialHeap;
+use super::compare::{Compare, MinOrder};
+use super::pairing::PairingHeap;
+use super::{Heap, MeldableHeap};
+
+/// Element tagged with its insertion sequence number.
+#[derive(Clone, Debug)]
+pub struct Stamped<T> {
+    seq: u64,
+    value: T,
+}
+
+impl<T> Stamped<T> {
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+/// Orders elements with `C` and serves equal elements first-in-first-out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Fifo<C = MinOrder>(pub C);
+
+impl<T, C: Compare<T>> Compare<Stamped<T>> for Fifo<C> {
+    fn compare(&self, a: &Stamped<T>, b: &Stamped<T>) -> Ordering {
+        self.0.compare(&a.value, &b.value).then(a.seq.cmp(&b.seq))
+    }
+}
+
+/// Sequence number of the next element inserted into any [`Stable`] heap.
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Heap that serves equal elements in insertion order.
+///
+/// Every element is stamped with a sequence number on insertion and [`Fifo`] uses it as the
+/// tiebreak, so only heaps built with [`Stable`] pay for the extra word per element. The numbers
+/// come from one counter shared by all stable heaps, so they stay unique across melds.
+#[derive(Clone, Debug, Default)]
+pub struct Stable<H> {
+    heap: H,
+}
+
+pub type StablePairingHeap<T, C = MinOrder> = Stable<PairingHeap<Stamped<T>, Fifo<C>>>;
+pub type StableBinomialHeap<T, C = MinOrder> = Stable<BinomialHeap<Stamped<T>, Fifo<C>>>;
+
+impl<T, C> Stable<PairingHeap<Stamped<T>, Fifo<C>>> {
+    pub const fn with_compare(cmp: C) -> Self {
+        Self {
+            heap: PairingHeap::with_compare(Fifo(cmp)),
+        }
+    }
+}
+
+impl<T: Ord> Stable<PairingHeap<Stamped<T>, Fifo>> {
+    pub const fn new() -> Self {
+        Self::with_compare(MinOrder)
+    }
+}
+
+impl<T, C> Stable<BinomialHeap<Stamped<T>, Fifo<C>>> {
+    pub const fn with_compare(cmp: C) -> Self {
+        Self {
+            heap: BinomialHeap::with_compare(Fifo(cmp)),
+        }
+    }
+}
+
+impl<T: Ord> Stable<BinomialHeap<Stamped<T>, Fifo>> {
+    pub const fn new() -> Self {
+        Self::with_compare(MinOrder)
+    }
+}
+
+impl<T, H: Heap<Item = Stamped<T>>> Stable<H> {
+    pub fn insert(&mut self, value: T) {
+        let seq = NEXT_SEQ.fetch_add(1, atomic::Ordering::Relaxed);
+        self.heap.insert(Stamped { seq, value });
+    }
+
+    pub fn delete_min(&mut self) -> Option<T> {
+        self.heap.delete_min().map(Stamped::into_value)
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.peek().map(Stamped::value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns the underlying heap of stamped elements.
+    pub fn inner(&self) -> &H {
+        &self.heap
+    }
+}
+
+impl<T, H: MeldableHeap<Item = Stamped<T>>> Stable<H> {
+    /// Moves all elements of `other` into `self`.
+    ///
+    /// Elements keep their sequence numbers, so equal elements of both heaps are served in the
+    /// order they were inserted, not in the order of the melds.
+    pub fn meld(&mut self, other: Self) {
+        self.heap.meld(other.heap);
+    }
+}
+
+impl<T, H: Heap<Item = Stamped<T>>> Heap for Stable<H> {
+    type Item = T;
+
+    fn insert(&mut self, item: T) {
+        Self::insert(self, item);
+    }
+
+    fn delete_min(&mut self) -> Option<T> {
+        Self::delete_min(self)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        Self::peek(self)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+impl<T, H: MeldableHeap<Item = Stamped<T>>> MeldableHeap for Stable<H> {
+    fn meld(&mut self, other: Self) {
+        Self::meld(self, other);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::heap::compare::ByKey;
+
+    fn check_fifo<H: Heap<Item = (u32, usize)>>(mut heap: H) {
+        for i in 0..100 {
+            heap.insert(((i * 7 % 5) as u32, i));
+        }
+        let mut last = None;
+        while let Some((key, i)) = heap.delete_min() {
+            if let Some((last_key, last_i)) = last {
+                assert!(last_key < key || (last_key == key && last_i < i));
+            }
+            last = Some((key, i));
+        }
+    }
+
+    #[test]
+    fn fifo_order() {
+        check_fifo(StablePairingHeap::with_compare(ByKey(MinOrder)));
+        check_fifo(StableBinomialHeap::with_compare(ByKey(MinOrder)));
+    }
+
+    #[test]
+    fn stable_meld() {
+        let mut a = StablePairingHeap::new();
+        let mut b = StablePairingHeap::new();
+        a.insert(1);
+        b.insert(0);
+        a.meld(b);
+        a.insert(1);
+        assert_eq!(a.peek(), Some(&0));
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.inner().len(), 3);
+
+        let mut a = StableBinomialHeap::with_compare(ByKey(MinOrder));
+        let mut b = StableBinomialHeap::with_compare(ByKey(MinOrder));
+        a.insert((1, "a0"));
+        b.insert((1, "b0"));
+        a.insert((1, "a1"));
+        a.insert((0, "a2"));
+        b.insert((1, "b1"));
+        a.insert((1, "a3"));
+        a.meld(b);
+        a.insert((1, "a4"));
+        let order: Vec<_> = std::iter::from_fn(|| a.delete_min())
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(order, ["a2", "a0", "b0", "a1", "b1", "a3", "a4"]);
+    }
+}
@@ -10,7 +10,7 @@ pub mod bench_fn {
             for _ in 0..len {
                 heap.insert(rng.gen_range(-100000000..100000000));
             }
-            let _v: Vec<i32> = (0..len).map(|_| heap.delete_min().unwrap()).collect();
+            let _v: Vec<i32> = heap.into_sorted_vec();
         }
     }
 
@@ -23,7 +23,7 @@ pub mod bench_fn {
             for _ in 0..len {
                 heap.insert(rng.gen_range(-100000000..100000000));
             }
-            let _v: Vec<i32> = (0..len).map(|_| heap.delete_min().unwrap()).collect();
+            let _v: Vec<i32> = heap.into_sorted_vec();
         }
     }
 }
@@ -1,5 +1,4 @@
-mod heap;
-use heap::pairing::PairingHeap;
+use heaps::heap::pairing::PairingHeap;
 fn main() {
     let mut heap = PairingHeap::new();
     heap.insert(100);